@@ -0,0 +1,84 @@
+extern crate mm_arl;
+extern crate rsrl;
+
+use mm_arl::{
+    TraderDomain,
+    dynamics::{ASDynamics, BrownianMotion, PoissonRate},
+    strategies::ExponentialUtilityStrategy,
+};
+use rsrl::domains::Domain;
+use std::time::{Duration, Instant};
+
+const WARMUP: usize = 100;
+const SAMPLES: usize = 2000;
+
+fn report(name: &str, mut times: Vec<Duration>, units: usize, unit: &str) {
+    times.sort();
+
+    let median = times[times.len() / 2];
+    let per_unit = median.as_nanos() as f64 / units as f64;
+
+    println!(
+        "{:<24} median {:>10.1?}  p10 {:>10.1?}  p90 {:>10.1?}  ({:.1} ns/{})",
+        name, median, times[times.len() / 10], times[times.len() * 9 / 10], per_unit, unit,
+    );
+}
+
+// Full episodes under the analytic strategy; the domain is reset rather than rebuilt so that
+// allocation is excluded from the measurement.
+fn bench_episode() {
+    let mut domain = TraderDomain::new(ASDynamics::<BrownianMotion, PoissonRate, _>::seeded(0), 0.0);
+    let strategy = ExponentialUtilityStrategy::new(1.5, 0.1, 2.0);
+
+    let run = |domain: &mut TraderDomain<_, _, _>| {
+        let mut steps = 0;
+
+        domain.reset();
+
+        loop {
+            let a = domain.analytic_action(&strategy);
+
+            steps += 1;
+
+            if domain.step(a).terminated() { return steps; }
+        }
+    };
+
+    for _ in 0..WARMUP { run(&mut domain); }
+
+    let mut steps = 0;
+    let times = (0..SAMPLES).map(|_| {
+        let start = Instant::now();
+
+        steps = run(&mut domain);
+
+        start.elapsed()
+    }).collect();
+
+    report("TraderDomain episode", times, steps, "step");
+}
+
+fn bench_innovate() {
+    const BATCH: usize = 200;
+
+    let mut dynamics = ASDynamics::<BrownianMotion, PoissonRate, _>::seeded(0);
+
+    for _ in 0..WARMUP * BATCH { dynamics.innovate(); }
+
+    let times = (0..SAMPLES).map(|_| {
+        dynamics.reset();
+
+        let start = Instant::now();
+
+        for _ in 0..BATCH { dynamics.innovate(); }
+
+        start.elapsed()
+    }).collect();
+
+    report("ASDynamics::innovate", times, BATCH, "call");
+}
+
+fn main() {
+    bench_episode();
+    bench_innovate();
+}
@@ -1,8 +1,11 @@
 use crate::{
-    dynamics::{ASDynamics, PoissonRate, BrownianMotionWithDrift},
-    strategies::LinearUtilityTerminalPenaltyStrategy,
+    dynamics::{
+        ASDynamics, ExecutionDynamics, PoissonRate, PriceDynamics, BrownianMotionWithDrift,
+        SettableDrift,
+    },
+    strategies::{QuotingStrategy, LinearUtilityTerminalPenaltyStrategy},
 };
-use rand::thread_rng;
+use rand::{Rng, rngs::ThreadRng, thread_rng};
 use rsrl::{
     domains::{Domain, Transition, Observation},
     spaces::{
@@ -14,9 +17,10 @@ use rsrl::{
 const MAX_DRIFT: f64 = 5.0;
 const INV_BOUNDS: [f64; 2] = [-50.0, 50.0];
 
-#[derive(Debug)]
-pub struct AdversaryDomain<P, E> {
-    pub dynamics: ASDynamics<P, E>,
+/// Clones share or split randomness as described for `TraderDomain`.
+#[derive(Clone, Debug)]
+pub struct AdversaryDomain<P, E, R = ThreadRng, S = LinearUtilityTerminalPenaltyStrategy> {
+    pub dynamics: ASDynamics<P, E, R>,
 
     pub inv: f64,
     pub inv_terminal: f64,
@@ -24,7 +28,8 @@ pub struct AdversaryDomain<P, E> {
     pub reward: f64,
     pub wealth: f64,
 
-    inv_strategy: LinearUtilityTerminalPenaltyStrategy,
+    inv_strategy: S,
+    clamped_actions: usize,
 }
 
 impl Default for AdversaryDomain<BrownianMotionWithDrift, PoissonRate> {
@@ -34,12 +39,47 @@ impl Default for AdversaryDomain<BrownianMotionWithDrift, PoissonRate> {
 }
 
 impl AdversaryDomain<BrownianMotionWithDrift, PoissonRate> {
-    pub fn new(dynamics: ASDynamics<BrownianMotionWithDrift, PoissonRate>, eta: f64) -> Self {
+    pub fn default_with_eta(eta: f64) -> Self {
+        let dynamics = ASDynamics::new(
+            0.005, 100.0, thread_rng(),
+            BrownianMotionWithDrift::new(0.005, 0.0, 2.0),
+            PoissonRate::default()
+        );
+
+        Self::new(dynamics, eta)
+    }
+}
+
+impl<R: Rng, S: QuotingStrategy> AdversaryDomain<BrownianMotionWithDrift, PoissonRate, R, S> {
+    /// Set the volatility of the price process, leaving its drift and step size unchanged.
+    pub fn with_volatility(mut self, volatility: f64) -> Self {
+        self.dynamics.price_dynamics.volatility = volatility;
+
+        self
+    }
+}
+
+impl<P: PriceDynamics + SettableDrift, R: Rng> AdversaryDomain<P, PoissonRate, R> {
+    pub fn new(dynamics: ASDynamics<P, PoissonRate, R>, eta: f64) -> Self {
         let inv_strategy = LinearUtilityTerminalPenaltyStrategy::new(
             dynamics.execution_dynamics.decay, eta,
         );
 
-        Self {
+        Self::with_strategy(dynamics, inv_strategy)
+    }
+}
+
+impl<P, R, S> AdversaryDomain<P, PoissonRate, R, S>
+where
+    P: PriceDynamics + SettableDrift,
+    R: Rng,
+    S: QuotingStrategy,
+{
+    pub fn with_strategy(
+        dynamics: ASDynamics<P, PoissonRate, R>,
+        inv_strategy: S,
+    ) -> Self {
+        AdversaryDomain {
             dynamics,
 
             inv: 0.0,
@@ -49,25 +89,16 @@ impl AdversaryDomain<BrownianMotionWithDrift, PoissonRate> {
             wealth: 0.0,
 
             inv_strategy,
+            clamped_actions: 0,
         }
     }
 
-    pub fn default_with_eta(eta: f64) -> Self {
-        let dynamics = ASDynamics::new(
-            0.005, 100.0, thread_rng(),
-            BrownianMotionWithDrift::new(0.005, 0.0, 2.0),
-            PoissonRate::default()
-        );
-
-        Self::new(dynamics, eta)
-    }
-
     fn do_executions(&mut self, ask_price: f64, bid_price: f64) {
         if self.inv > INV_BOUNDS[0] {
             if let Some(ask_offset) = self.dynamics.try_execute_ask(ask_price) {
                 self.inv -= 1.0;
                 self.reward -= ask_offset;
-                self.wealth += ask_price;
+                self.wealth += self.dynamics.price + ask_offset;
             }
         }
 
@@ -75,7 +106,7 @@ impl AdversaryDomain<BrownianMotionWithDrift, PoissonRate> {
             if let Some(bid_offset) = self.dynamics.try_execute_bid(bid_price) {
                 self.inv += 1.0;
                 self.reward -= bid_offset;
-                self.wealth -= bid_price;
+                self.wealth -= self.dynamics.price - bid_offset;
             }
         }
     }
@@ -90,7 +121,7 @@ impl AdversaryDomain<BrownianMotionWithDrift, PoissonRate> {
         let ask_price = self.dynamics.price + ask_offset;
         let bid_price = self.dynamics.price - bid_offset;
 
-        self.dynamics.price_dynamics.drift = MAX_DRIFT * (2.0 * drift - 1.0);
+        self.dynamics.price_dynamics.set_drift(MAX_DRIFT * (2.0 * drift - 1.0));
         self.reward = -(self.inv * self.dynamics.innovate());
 
         self.do_executions(ask_price, bid_price);
@@ -105,9 +136,30 @@ impl AdversaryDomain<BrownianMotionWithDrift, PoissonRate> {
     }
 
     fn is_terminal(&self) -> bool { self.dynamics.time >= 1.0 }
+
+    /// Drift applied to the price process on the last step, in price units.
+    pub fn current_drift(&self) -> f64 { self.dynamics.price_dynamics.drift() }
+
+    /// Loose per-step reward interval, assuming the trader's quotes are non-negative.
+    pub fn reward_bounds(&self) -> (f64, f64) {
+        let inv_max = INV_BOUNDS[1].max(-INV_BOUNDS[0]) + 1.0;
+        let holding = inv_max * (self.dynamics.increment_bound() + MAX_DRIFT * self.dynamics.dt);
+        let spread = 2.0 * self.dynamics.execution_dynamics.max_offset();
+
+        (-holding - spread, holding)
+    }
+
+    pub fn clamped_actions(&self) -> usize { self.clamped_actions }
+
+    pub fn reset_clamped_actions(&mut self) { self.clamped_actions = 0; }
 }
 
-impl Domain for AdversaryDomain<BrownianMotionWithDrift, PoissonRate> {
+impl<P, R, S> Domain for AdversaryDomain<P, PoissonRate, R, S>
+where
+    P: PriceDynamics + SettableDrift,
+    R: Rng,
+    S: QuotingStrategy,
+{
     type StateSpace = ProductSpace<Interval>;
     type ActionSpace = Interval;
 
@@ -123,7 +175,11 @@ impl Domain for AdversaryDomain<BrownianMotionWithDrift, PoissonRate> {
 
     fn step(&mut self, action: f64) -> Transition<Vec<f64>, f64> {
         let from = self.emit();
-        let action = action.min(1.0).max(0.0);
+        let clamped = action.clamp(0.0, 1.0);
+
+        if clamped != action { self.clamped_actions += 1; }
+
+        let action = clamped;
 
         self.update_state(action);
 
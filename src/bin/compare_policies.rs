@@ -0,0 +1,208 @@
+extern crate clap;
+extern crate mm_arl;
+extern crate csv;
+extern crate rsrl;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+
+use mm_arl::{
+    INVENTORY_BUCKETS,
+    EpisodeSummary,
+    TraderDomain,
+    inventory_profile_stats,
+    simulate_batch_seeded,
+    simulate_batch_seeded_with,
+    checkpoint::{SaveLoad, TraderAgent},
+    dynamics::ASDynamics,
+    experiment::ExperimentError,
+    strategies::{
+        QuotingStrategy,
+        LinearUtilityStrategy,
+        LinearUtilityTerminalPenaltyStrategy,
+        ExponentialUtilityStrategy,
+    },
+    utils::{Estimate, to_offsets},
+};
+use clap::{App, Arg};
+use rsrl::{domains::Domain, policies::Policy};
+use std::process;
+
+#[derive(Debug, Serialize)]
+struct Record {
+    pub policy: String,
+
+    pub wealth_mean: f64,
+    pub wealth_stddev: f64,
+
+    pub inv_mean: f64,
+    pub inv_stddev: f64,
+
+    pub spread_mean: f64,
+    pub spread_stddev: f64,
+
+    /// Left empty when the wealth has no spread across episodes.
+    pub sharpe: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+struct BucketRecord {
+    pub policy: String,
+    pub bucket: usize,
+    pub time_start: f64,
+
+    pub inv_mean: f64,
+    pub inv_stddev: f64,
+}
+
+fn main() {
+    let matches = App::new("Policy comparison")
+        .arg(Arg::with_name("csv_path")
+                .index(1)
+                .required(true))
+        .arg(Arg::with_name("n_episodes")
+                .index(2)
+                .required(true))
+        .arg(Arg::with_name("seed")
+                .long("seed")
+                .required(false)
+                .default_value("0"))
+        .arg(Arg::with_name("gamma")
+                .long("gamma")
+                .required(false)
+                .default_value("0.1"))
+        .arg(Arg::with_name("eta")
+                .long("eta")
+                .required(false)
+                .default_value("0.0"))
+        .arg(Arg::with_name("inventory_buckets")
+                .long("inventory-buckets")
+                .takes_value(true))
+        .arg(Arg::with_name("checkpoint")
+                .long("checkpoint")
+                .takes_value(true)
+                .help("Trader checkpoint to include in the comparison"))
+        .get_matches();
+
+    let csv_path = matches.value_of("csv_path").unwrap();
+    let n_episodes: usize = matches.value_of("n_episodes").unwrap().parse().unwrap();
+    let seed: u64 = matches.value_of("seed").unwrap().parse().unwrap();
+    let gamma: f64 = matches.value_of("gamma").unwrap().parse().unwrap();
+    let eta: f64 = matches.value_of("eta").unwrap().parse().unwrap();
+    let buckets_path = matches.value_of("inventory_buckets");
+    let checkpoint_path = matches.value_of("checkpoint");
+
+    let result = run(csv_path, n_episodes, seed, gamma, eta, buckets_path, checkpoint_path);
+
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+
+        process::exit(1);
+    }
+}
+
+fn run(
+    csv_path: &str,
+    n_episodes: usize,
+    seed: u64,
+    gamma: f64,
+    eta: f64,
+    buckets_path: Option<&str>,
+    checkpoint_path: Option<&str>,
+) -> Result<(), ExperimentError> {
+    let agent = checkpoint_path.map(TraderAgent::load).transpose()?;
+    let domain_spec = match agent {
+        Some((ref spec, _)) => Some(spec.trader_domain()?.clone()),
+        None => None,
+    };
+
+    // Every policy runs in the agent's training domain, if there is one, so the comparison is
+    // like for like:
+    let domain_builder = |s: u64| {
+        let domain = TraderDomain::new(ASDynamics::seeded(s), eta);
+
+        match domain_spec {
+            Some(ref spec) => spec.apply(domain),
+            None => domain,
+        }
+    };
+
+    let domain = domain_builder(seed);
+    let k = domain.dynamics.execution_dynamics.decay;
+    let volatility = domain.dynamics.price_dynamics.volatility;
+
+    let policies: Vec<(&str, Box<dyn QuotingStrategy + Sync>)> = vec![
+        ("linear_utility", Box::new(LinearUtilityStrategy::new(k))),
+        ("linear_utility_penalty", Box::new(LinearUtilityTerminalPenaltyStrategy::new(k, eta))),
+        ("exponential_utility", Box::new(ExponentialUtilityStrategy::new(k, gamma, volatility))),
+    ];
+
+    // Every policy sees the same seeds, giving paired comparisons:
+    let mut results: Vec<(&str, Vec<EpisodeSummary>)> = policies.iter()
+        .map(|(name, strategy)| {
+            (*name, simulate_batch_seeded(n_episodes, seed, domain_builder, &**strategy))
+        })
+        .collect();
+
+    if let Some((spec, agent)) = agent {
+        println!("Loaded a degree {} policy over {} inputs.", spec.basis_degree, spec.n_inputs);
+
+        let policy = agent.policy;
+        let summaries = simulate_batch_seeded_with(n_episodes, seed, domain_builder, |d| {
+            let a = policy.mpa(d.emit().state());
+
+            to_offsets(a.0, a.1)
+        });
+
+        results.push(("agent", summaries));
+    }
+
+    let mut file_logger = csv::Writer::from_path(csv_path)?;
+    let mut buckets_logger = buckets_path.map(csv::Writer::from_path).transpose()?;
+
+    for (name, summaries) in results.iter() {
+        let pnls: Vec<_> = summaries.iter().map(|s| s.terminal_wealth).collect();
+        let terminal_qs: Vec<_> = summaries.iter().map(|s| s.terminal_inventory).collect();
+        let average_spread: Vec<_> = summaries.iter().map(|s| s.avg_spread).collect();
+
+        let pnl_est = Estimate::from_slice(&pnls);
+        let inv_est = Estimate::from_slice(&terminal_qs);
+        let spd_est = Estimate::from_slice(&average_spread);
+
+        println!("{}: PnL {} pm {}", name, pnl_est.0, pnl_est.1);
+
+        file_logger.serialize(Record {
+            policy: name.to_string(),
+
+            wealth_mean: pnl_est.0,
+            wealth_stddev: pnl_est.1,
+
+            inv_mean: inv_est.0,
+            inv_stddev: inv_est.1,
+
+            spread_mean: spd_est.0,
+            spread_stddev: spd_est.1,
+
+            sharpe: if pnl_est.1 > 0.0 { Some(pnl_est.0 / pnl_est.1) } else { None },
+        })?;
+
+        if let Some(ref mut logger) = buckets_logger {
+            for (bucket, est) in inventory_profile_stats(summaries).into_iter().enumerate() {
+                logger.serialize(BucketRecord {
+                    policy: name.to_string(),
+                    bucket,
+                    time_start: bucket as f64 / INVENTORY_BUCKETS as f64,
+
+                    inv_mean: est.0,
+                    inv_stddev: est.1,
+                })?;
+            }
+        }
+    }
+
+    file_logger.flush()?;
+
+    if let Some(ref mut logger) = buckets_logger { logger.flush()?; }
+
+    Ok(())
+}
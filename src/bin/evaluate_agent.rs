@@ -0,0 +1,218 @@
+extern crate mm_arl;
+extern crate clap;
+extern crate rand;
+extern crate rsrl;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+
+use mm_arl::{
+    TraderDomain,
+    checkpoint::{SaveLoad, TraderAgent},
+    dynamics::{ASDynamics, BrownianMotion, PoissonRate},
+    experiment::{ExperimentError, load_config, validate_save_dir, write_csv},
+    utils::{Estimate, cvar, to_offsets},
+};
+use clap::{App, Arg};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use rsrl::{domains::Domain, policies::Policy};
+use std::process;
+
+const DT: f64 = 0.005;
+
+#[derive(Debug, Serialize)]
+struct SummaryRecord {
+    pub seed: String,
+    pub episodes: usize,
+
+    pub wealth_mean: f64,
+    pub wealth_stddev: f64,
+    /// Left empty for a seed without episodes.
+    pub wealth_cvar_95: Option<f64>,
+
+    pub inv_mean: f64,
+    pub inv_stddev: f64,
+
+    pub spread_mean: f64,
+    pub spread_stddev: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct EpisodeRecord {
+    pub seed: u64,
+    pub episode: usize,
+
+    pub wealth: f64,
+    pub inv_terminal: f64,
+    pub average_spread: f64,
+}
+
+/// The subset of `train_trader`'s configuration that sets the dynamics and reward; the rest of
+/// the domain is rebuilt from the checkpoint.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct Config {
+    price_dynamics: BrownianMotion,
+    execution_dynamics: PoissonRate,
+    eta: f64,
+    normalize_reward: bool,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            price_dynamics: BrownianMotion::new(DT, 2.0),
+            execution_dynamics: PoissonRate::new(DT, 140.0, 1.5),
+            eta: 0.0,
+            normalize_reward: false,
+        }
+    }
+}
+
+fn main() {
+    let matches = App::new("RL trader evaluation")
+        .arg(Arg::with_name("checkpoint_path")
+                .index(1)
+                .required(true))
+        .arg(Arg::with_name("save_dir")
+                .index(2)
+                .required(true))
+        .arg(Arg::with_name("config")
+                .long("config")
+                .takes_value(true))
+        .arg(Arg::with_name("eval_episodes")
+                .long("eval-episodes")
+                .required(false)
+                .default_value("10000"))
+        .arg(Arg::with_name("seeds")
+                .long("seeds")
+                .required(false)
+                .default_value("1"))
+        .arg(Arg::with_name("seed")
+                .long("seed")
+                .required(false)
+                .default_value("0"))
+        .get_matches();
+
+    let checkpoint_path = matches.value_of("checkpoint_path").unwrap();
+    let save_dir = matches.value_of("save_dir").unwrap();
+    let eval_episodes: usize = matches.value_of("eval_episodes").unwrap().parse().unwrap();
+    let n_seeds: u64 = matches.value_of("seeds").unwrap().parse().unwrap();
+    let seed: u64 = matches.value_of("seed").unwrap().parse().unwrap();
+
+    let config = matches.value_of("config")
+        .map_or_else(|| Ok(Config::default()), load_config);
+    let result = config.and_then(|config| {
+        run(config, checkpoint_path, save_dir, eval_episodes, seed..(seed + n_seeds))
+    });
+
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+
+        process::exit(1);
+    }
+}
+
+fn summarise(seed: String, episodes: &[EpisodeRecord]) -> SummaryRecord {
+    let mut pnls: Vec<_> = episodes.iter().map(|e| e.wealth).collect();
+    let terminal_qs: Vec<_> = episodes.iter().map(|e| e.inv_terminal).collect();
+    let average_spread: Vec<_> = episodes.iter().map(|e| e.average_spread).collect();
+
+    let pnl_est = Estimate::from_slice(&pnls);
+    let inv_est = Estimate::from_slice(&terminal_qs);
+    let spd_est = Estimate::from_slice(&average_spread);
+
+    SummaryRecord {
+        seed,
+        episodes: episodes.len(),
+
+        wealth_mean: pnl_est.0,
+        wealth_stddev: pnl_est.1,
+        wealth_cvar_95: cvar(&mut pnls, 0.05),
+
+        inv_mean: inv_est.0,
+        inv_stddev: inv_est.1,
+
+        spread_mean: spd_est.0,
+        spread_stddev: spd_est.1,
+    }
+}
+
+fn run<I: Iterator<Item = u64>>(
+    config: Config,
+    checkpoint_path: &str,
+    save_dir: &str,
+    eval_episodes: usize,
+    seeds: I,
+) -> Result<(), ExperimentError> {
+    let Config { price_dynamics, execution_dynamics, eta, normalize_reward } = config;
+
+    validate_save_dir(save_dir)?;
+    price_dynamics.noise.validate()?;
+    execution_dynamics.validate()?;
+
+    if execution_dynamics.saturates() {
+        eprintln!("Warning: match probabilities saturate at small offsets.");
+    }
+
+    let (spec, agent) = TraderAgent::load(checkpoint_path)?;
+    let policy = agent.policy;
+
+    let domain_spec = spec.trader_domain()?;
+
+    println!("Loaded a degree {} policy over {} inputs.", spec.basis_degree, spec.n_inputs);
+
+    let mut episodes = vec![];
+    let mut summaries = vec![];
+
+    for seed in seeds {
+        let mut domain_seeds = StdRng::seed_from_u64(seed);
+
+        let from = episodes.len();
+
+        for episode in 0..eval_episodes {
+            let dynamics = ASDynamics::new(
+                DT, 100.0, StdRng::seed_from_u64(domain_seeds.gen()),
+                price_dynamics.clone(), execution_dynamics.clone(),
+            );
+            let mut domain = domain_spec.apply(
+                TraderDomain::new(dynamics, eta).with_normalized_reward(normalize_reward)
+            );
+
+            loop {
+                let a = policy.mpa(domain.emit().state());
+                let t = domain.step(to_offsets(a.0, a.1));
+
+                if t.terminated() { break }
+            }
+
+            episodes.push(EpisodeRecord {
+                seed,
+                episode,
+
+                wealth: domain.wealth,
+                inv_terminal: domain.inv_terminal,
+                average_spread: domain.time_weighted_avg_spread(),
+            });
+        }
+
+        summaries.push(summarise(seed.to_string(), &episodes[from..]));
+    }
+
+    summaries.push(summarise("all".to_string(), &episodes));
+
+    for s in summaries.iter() {
+        println!(
+            "seed {}: wealth {} (CVaR-95: {}), inv_terminal {}",
+            s.seed,
+            Estimate(s.wealth_mean, s.wealth_stddev),
+            s.wealth_cvar_95.map_or_else(|| "n/a".to_string(), |c| c.to_string()),
+            Estimate(s.inv_mean, s.inv_stddev),
+        );
+    }
+
+    write_csv(format!("{}/summary.csv", save_dir), &summaries)?;
+    write_csv(format!("{}/episodes.csv", save_dir), &episodes)?;
+
+    Ok(())
+}
@@ -0,0 +1,96 @@
+extern crate clap;
+extern crate rand;
+extern crate rayon;
+extern crate mm_arl;
+extern crate csv;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+
+use mm_arl::{
+    TraderDomain,
+    aggregate, simulate_batch,
+    dynamics::{ASDynamics, BrownianMotion, PoissonRate},
+    strategies::ExponentialUtilityStrategy,
+};
+use clap::{App, Arg};
+use rand::thread_rng;
+use rayon::prelude::*;
+
+const DT: f64 = 0.005;
+
+#[derive(Debug, Serialize)]
+struct Record {
+    pub decay: f64,
+
+    pub wealth_mean: f64,
+    pub wealth_stddev: f64,
+
+    pub inv_mean: f64,
+    pub inv_stddev: f64,
+
+    pub spread_mean: f64,
+    pub spread_stddev: f64,
+}
+
+fn simulate(n_simulations: usize, gamma: f64, decay: f64) -> Record {
+    let domain_builder = move || TraderDomain::new(ASDynamics::new(
+        DT, 100.0, thread_rng(),
+        BrownianMotion::new(DT, 2.0),
+        PoissonRate::new(DT, 140.0, decay),
+    ), 0.0);
+
+    let quotes = ExponentialUtilityStrategy::new(
+        decay, gamma,
+        domain_builder().dynamics.price_dynamics.volatility,
+    );
+
+    let stats = aggregate(&simulate_batch(n_simulations, domain_builder, &quotes));
+
+    Record {
+        decay,
+
+        wealth_mean: stats.terminal_wealth.0,
+        wealth_stddev: stats.terminal_wealth.1,
+
+        inv_mean: stats.terminal_inventory.0,
+        inv_stddev: stats.terminal_inventory.1,
+
+        spread_mean: stats.avg_spread.0,
+        spread_stddev: stats.avg_spread.1,
+    }
+}
+
+fn main() {
+    let matches = App::new("AS execution decay sweep")
+        .arg(Arg::with_name("csv_path")
+                .index(1)
+                .required(true))
+        .arg(Arg::with_name("n_simulations")
+                .index(2)
+                .required(true))
+        .arg(Arg::with_name("gamma")
+                .long("gamma")
+                .required(false)
+                .default_value("0.1"))
+        .get_matches();
+
+    let csv_path = matches.value_of("csv_path").unwrap();
+    let n_simulations: usize = matches.value_of("n_simulations").unwrap().parse().unwrap();
+    let gamma: f64 = matches.value_of("gamma").unwrap().parse().unwrap();
+
+    let mut records: Vec<_> = (1..51)
+        .into_par_iter()
+        .map(|i| 0.1 * i as f64)
+        .map(|k| simulate(n_simulations, gamma, k))
+        .collect();
+    records.par_sort_unstable_by(|a, b| a.decay.partial_cmp(&b.decay).unwrap());
+
+    let mut file_logger = csv::Writer::from_path(csv_path).unwrap();
+
+    for r in records {
+        file_logger.serialize(r).ok();
+    }
+
+    file_logger.flush().ok();
+}
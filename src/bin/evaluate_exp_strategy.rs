@@ -1,6 +1,5 @@
 extern crate clap;
 extern crate rand;
-extern crate rsrl;
 extern crate rayon;
 extern crate mm_arl;
 extern crate csv;
@@ -9,87 +8,107 @@ extern crate serde;
 extern crate serde_derive;
 
 use mm_arl::{
+    EpisodeSummary,
     TraderDomain,
+    dynamics::ASDynamics,
+    simulate_batch_seeded,
     strategies::ExponentialUtilityStrategy,
     utils::Estimate,
 };
 use clap::{App, Arg};
 use rayon::prelude::*;
-use rsrl::domains::Domain;
 
+/// Per-`eta` summary over `seeds` independent runs.
+///
+/// The `*_stddev` columns are the within-run spread, averaged over runs, and the `*_seed_stddev`
+/// columns the spread of the per-run means.
 #[derive(Debug, Serialize)]
 struct Record {
     pub eta: f64,
+    pub seeds: usize,
 
     pub wealth_mean: f64,
     pub wealth_stddev: f64,
+    pub wealth_seed_stddev: f64,
 
     pub inv_mean: f64,
     pub inv_stddev: f64,
+    pub inv_seed_stddev: f64,
 
     pub spread_mean: f64,
     pub spread_stddev: f64,
+    pub spread_seed_stddev: f64,
+
+    pub realized_spread_mean: f64,
+    pub realized_spread_stddev: f64,
+    pub realized_spread_seed_stddev: f64,
 }
 
-fn simulate(n_simulations: usize, eta: f64) -> Record {
-    let mut pnls = vec![];
-    let mut terminal_qs = vec![];
-    let mut average_spread = vec![];
-
-    for _ in 0..n_simulations {
-        let mut domain = TraderDomain::default();
-        let quotes = ExponentialUtilityStrategy::new(
-            domain.dynamics.execution_dynamics.decay, eta,
-            domain.dynamics.price_dynamics.volatility,
-        );
-
-        let mut a = quotes.compute(
-            domain.dynamics.time,
-            domain.dynamics.price,
-            domain.inv,
-        );
-
-        let mut i = 1;
-        let mut spread_sum = a[0] + a[1];
-
-        loop {
-            let t = domain.step(a);
-
-            if t.terminated() {
-                pnls.push(domain.wealth);
-                terminal_qs.push(domain.inv_terminal);
-                average_spread.push(spread_sum / i as f64);
-
-                break
-            } else {
-                a = quotes.compute(
-                    domain.dynamics.time,
-                    domain.dynamics.price,
-                    domain.inv,
-                );
-
-                i += 1;
-                spread_sum += a[0] + a[1];
-            }
-        }
-    }
+/// Returns `[mean, within-run stddev, across-seed stddev]` of one metric.
+fn aggregate<F>(runs: &[Vec<EpisodeSummary>], metric: F) -> [f64; 3]
+where
+    F: Fn(&EpisodeSummary) -> f64,
+{
+    let (means, stddevs): (Vec<_>, Vec<_>) = runs.iter()
+        .map(|run| {
+            let values: Vec<_> = run.iter().map(&metric).collect();
+            let est = Estimate::from_slice(&values);
+
+            (est.0, est.1)
+        })
+        .unzip();
+
+    let across = Estimate::from_slice(&means);
+    let within = Estimate::from_slice(&stddevs);
+
+    [across.0, within.0, across.1]
+}
 
-    // Summarise results:
-    let pnl_est = Estimate::from_slice(&pnls);
-    let inv_est = Estimate::from_slice(&terminal_qs);
-    let spd_est = Estimate::from_slice(&average_spread);
+fn simulate(n_simulations: usize, n_seeds: usize, eta: f64) -> Record {
+    let domain = TraderDomain::default();
+    let quotes = ExponentialUtilityStrategy::new(
+        domain.dynamics.execution_dynamics.decay, eta,
+        domain.dynamics.price_dynamics.volatility,
+    );
+
+    // Run k draws episode seeds from its own disjoint block, shared across all values of eta:
+    let runs: Vec<_> = (0..n_seeds)
+        .into_par_iter()
+        .map(|k| simulate_batch_seeded(
+            n_simulations, (k * n_simulations) as u64,
+            |seed| TraderDomain::new(ASDynamics::seeded(seed), 0.0),
+            &quotes,
+        ))
+        .collect();
+
+    let [wealth_mean, wealth_stddev, wealth_seed_stddev] =
+        aggregate(&runs, |s| s.terminal_wealth);
+    let [inv_mean, inv_stddev, inv_seed_stddev] =
+        aggregate(&runs, |s| s.terminal_inventory);
+    let [spread_mean, spread_stddev, spread_seed_stddev] =
+        aggregate(&runs, |s| s.avg_spread);
+    let [realized_spread_mean, realized_spread_stddev, realized_spread_seed_stddev] =
+        aggregate(&runs, |s| s.realized_spread);
 
     Record {
-        eta: eta,
+        eta,
+        seeds: n_seeds,
+
+        wealth_mean,
+        wealth_stddev,
+        wealth_seed_stddev,
 
-        wealth_mean: pnl_est.0,
-        wealth_stddev: pnl_est.1,
+        inv_mean,
+        inv_stddev,
+        inv_seed_stddev,
 
-        inv_mean: inv_est.0,
-        inv_stddev: inv_est.1,
+        spread_mean,
+        spread_stddev,
+        spread_seed_stddev,
 
-        spread_mean: spd_est.0,
-        spread_stddev: spd_est.1,
+        realized_spread_mean,
+        realized_spread_stddev,
+        realized_spread_seed_stddev,
     }
 }
 
@@ -101,16 +120,21 @@ fn main() {
         .arg(Arg::with_name("n_simulations")
                 .index(2)
                 .required(true))
+        .arg(Arg::with_name("seeds")
+                .long("seeds")
+                .required(false)
+                .default_value("1"))
         .get_matches();
 
     let csv_path = matches.value_of("csv_path").unwrap();
     let n_simulations: usize = matches.value_of("n_simulations").unwrap().parse().unwrap();
+    let n_seeds: usize = matches.value_of("seeds").unwrap().parse().unwrap();
 
     let mut records: Vec<_> = (1..101)
         .into_par_iter()
         .map(|i| 0.01 * i as f64)
         .chain(rayon::iter::once(0.001))
-        .map(|g| simulate(n_simulations, g))
+        .map(|g| simulate(n_simulations, n_seeds, g))
         .collect();
     records.par_sort_unstable_by(|a, b| a.eta.partial_cmp(&b.eta).unwrap());
 
@@ -1,39 +1,17 @@
 extern crate clap;
 extern crate rand;
-extern crate rsrl;
 extern crate rayon;
 extern crate mm_arl;
 
 use mm_arl::{
     TraderDomain,
+    simulate_batch,
     dynamics::ASDynamics,
     strategies::LinearUtilityStrategy,
-    utils::{mean_var, median_quantiles},
+    utils::{self, Estimate, cvar, mean_var, median_quantiles},
 };
 use clap::{App, Arg};
 use rayon::prelude::*;
-use rsrl::domains::Domain;
-
-fn simulate_once(risk_param: f64) -> (f64, f64) {
-    let mut domain = TraderDomain::new(ASDynamics::default_with_drift(0.0), risk_param);
-
-    let quotes = LinearUtilityStrategy::new(
-        domain.dynamics.execution_dynamics.decay,
-    );
-
-    loop {
-        let a = quotes.compute(
-            domain.dynamics.time,
-            domain.dynamics.price,
-            domain.inv,
-        );
-        let t = domain.step(a);
-
-        if t.terminated() {
-            return (domain.wealth, domain.inv_terminal)
-        }
-    }
-}
 
 fn main() {
     let matches = App::new("AS inventory strategy simulator")
@@ -42,23 +20,76 @@ fn main() {
                 .required(true))
         .arg(Arg::with_name("risk_param")
                 .index(2)
-                .required(true))
+                .required_unless("risk_params"))
+        .arg(Arg::with_name("risk_params")
+                .long("risk-params")
+                .takes_value(true)
+                .conflicts_with("risk_param"))
         .get_matches();
 
     let n_simulations: usize = matches.value_of("n_simulations").unwrap().parse().unwrap();
-    let risk_param: f64 = matches.value_of("risk_param").unwrap().parse().unwrap();
 
-    let (mut pnls, mut terminal_qs): (Vec<_>, Vec<_>) =
-        (0..n_simulations).into_par_iter().map(move |_| simulate_once(risk_param)).unzip();
+    match matches.value_of("risk_params") {
+        Some(list) => {
+            let risk_params: Vec<f64> = list.split(',')
+                .map(|v| v.trim().parse().unwrap())
+                .collect();
+            let rows: Vec<_> = risk_params.iter().map(|&risk_param| {
+                println!("risk_param = {}:", risk_param);
+
+                (risk_param, evaluate(n_simulations, risk_param))
+            }).collect();
 
-    pnls.par_sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
-    terminal_qs.par_sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
+            println!();
+            print_table(&rows);
+        },
+        None => {
+            let risk_param: f64 = matches.value_of("risk_param").unwrap().parse().unwrap();
+
+            evaluate(n_simulations, risk_param);
+        },
+    }
+}
+
+// Prints the full breakdown for one value and returns the PnL and inventory estimates:
+fn evaluate(n_simulations: usize, risk_param: f64) -> [Estimate; 2] {
+    let domain_builder = move || TraderDomain::new(ASDynamics::default_with_drift(0.0), risk_param);
+    let quotes = LinearUtilityStrategy::new(
+        domain_builder().dynamics.execution_dynamics.decay,
+    );
+
+    let (mut pnls, mut terminal_qs): (Vec<_>, Vec<_>) = simulate_batch(n_simulations, domain_builder, &quotes)
+        .into_iter()
+        .map(|s| (s.terminal_wealth, s.terminal_inventory))
+        .unzip();
+
+    pnls.par_sort_unstable_by(f64::total_cmp);
+    terminal_qs.par_sort_unstable_by(f64::total_cmp);
 
     let [mean, var] = mean_var(&pnls);
     let [q25, median, q75] = median_quantiles(&pnls);
     println!("PnL: {} pm {} | {} < {} < {}", mean, var.sqrt(), q25, median, q75);
+    if let (Some(cvar_95), Some(var_95)) = (cvar(&mut pnls, 0.05), utils::var(&mut pnls, 0.05)) {
+        println!("PnL CVaR-95: {} (VaR-95: {})", cvar_95, var_95);
+    }
+
+    let pnl_est = Estimate(mean, var.sqrt());
 
     let [mean, var] = mean_var(&terminal_qs);
     let [q25, median, q75] = median_quantiles(&terminal_qs);
     println!("Inv: {} pm {} | {} < {} < {}", mean, var.sqrt(), q25, median, q75);
+
+    [pnl_est, Estimate(mean, var.sqrt())]
+}
+
+fn print_table(rows: &[(f64, [Estimate; 2])]) {
+    println!("{:>12} | {:>22} | {:>22}", "risk_param", "PnL", "Inv");
+    println!("{:-<12}-+-{:-<22}-+-{:-<22}", "", "", "");
+
+    for (risk_param, [pnl, inv]) in rows {
+        println!(
+            "{:>12} | {:>22} | {:>22}",
+            risk_param, format!("{:.4}", pnl), format!("{:.4}", inv),
+        );
+    }
 }
@@ -11,23 +11,21 @@ extern crate serde_derive;
 
 use mm_arl::{
     AdversaryDomain,
-    utils::Estimate,
+    checkpoint::{AgentSpec, SaveLoad, adversary_agent},
+    experiment::{ExperimentError, catch_interrupt, interrupted, validate_save_dir},
+    utils::RunningEstimate,
 };
 use clap::{App, Arg};
 use rand::thread_rng;
 use rsrl::{
     OnlineLearner,
-    control::{Controller, ac::TDAC},
+    control::Controller,
     domains::Domain,
-    fa::{
-        TransformedLFA,
-        linear::{LFA, basis::{Projector, Polynomial}, optim::SGD},
-        transforms::Softplus,
-    },
     logging,
-    policies::{Policy, Beta},
-    prediction::{ValuePredictor, td::TD},
+    policies::Policy,
+    prediction::ValuePredictor,
 };
+use std::process;
 
 #[derive(Debug, Serialize)]
 struct Record {
@@ -49,6 +47,16 @@ struct Record {
     pub drift_bear: f64,
 }
 
+struct Config {
+    save_dir: String,
+    eval_interval: usize,
+    eval_episodes: usize,
+    eta: f64,
+    volatility: f64,
+    pretrain: usize,
+    basis_degree: u8,
+}
+
 fn main() {
     let matches = App::new("RL adversary")
         .arg(Arg::with_name("save_dir")
@@ -57,44 +65,74 @@ fn main() {
         .arg(Arg::with_name("eval_interval")
                 .index(2)
                 .required(true))
+        .arg(Arg::with_name("eval_episodes")
+                .long("eval-episodes")
+                .required(false)
+                .default_value("1000"))
         .arg(Arg::with_name("eta")
                 .long("eta")
                 .required(false)
                 .default_value("0.0"))
+        .arg(Arg::with_name("volatility")
+                .long("volatility")
+                .required(false)
+                .default_value("2.0"))
+        .arg(Arg::with_name("pretrain")
+                .long("pretrain")
+                .required(false)
+                .default_value("1000"))
+        .arg(Arg::with_name("basis_degree")
+                .long("basis-degree")
+                .required(false)
+                .default_value("3"))
         .get_matches();
 
-    let save_dir = matches.value_of("save_dir").unwrap();
-    let eval_interval: usize = matches.value_of("eval_interval").unwrap().parse().unwrap();
-    let eta: f64 = matches.value_of("eta").unwrap().parse().unwrap();
+    let config = Config {
+        save_dir: matches.value_of("save_dir").unwrap().to_string(),
+        eval_interval: matches.value_of("eval_interval").unwrap().parse().unwrap(),
+        eval_episodes: matches.value_of("eval_episodes").unwrap().parse().unwrap(),
+        eta: matches.value_of("eta").unwrap().parse().unwrap(),
+        volatility: matches.value_of("volatility").unwrap().parse().unwrap(),
+        pretrain: matches.value_of("pretrain").unwrap().parse().unwrap(),
+        basis_degree: matches.value_of("basis_degree").unwrap().parse().unwrap(),
+    };
 
-    let logger = logging::root(logging::stdout());
-    let mut file_logger = csv::Writer::from_path(format!("{}/results.csv", save_dir)).unwrap();
+    if let Err(e) = run(config) {
+        eprintln!("Error: {}", e);
 
-    let mut rng = thread_rng();
+        process::exit(1);
+    }
+}
 
-    let domain_builder = || AdversaryDomain::default_with_eta(eta);
+fn run(config: Config) -> Result<(), ExperimentError> {
+    let Config {
+        save_dir, eval_interval, eval_episodes, eta, volatility, pretrain, basis_degree,
+    } = config;
 
-    // Build policy:
-    let basis = Polynomial::new(2, 3).with_constant();
+    validate_save_dir(&save_dir)?;
 
-    let policy = Beta::new(
-        TransformedLFA::scalar(basis.clone(), Softplus),
-        TransformedLFA::scalar(basis.clone(), Softplus),
-    );
+    let logger = logging::root(logging::stdout());
+    let mut file_logger = csv::Writer::from_path(format!("{}/results.csv", save_dir))?;
+
+    let mut rng = thread_rng();
 
-    // Build critic:
-    let critic = TD::new(LFA::scalar(basis, SGD(1.0)), 0.1, 1.0);
+    let domain_builder = || AdversaryDomain::default_with_eta(eta).with_volatility(volatility);
 
     // Build agent:
-    let mut agent = TDAC::new(
-        critic,
-        policy,
-        0.001,
-        1.0,
-    );
+    let spec = AgentSpec {
+        n_inputs: 2,
+        basis_degree,
+
+        sgd_lr: 1.0,
+        critic_lr: 0.1,
+        actor_lr: 0.001,
+        gamma: 1.0,
+        domain: None,
+    };
+    let mut agent = adversary_agent(&spec);
 
     // Pre-train value function:
-    for _ in 0..1000 {
+    for _ in 0..pretrain {
         let mut domain = domain_builder();
         let mut a = agent.sample_behaviour(&mut rng, domain.emit().state());
 
@@ -111,7 +149,11 @@ fn main() {
         }
     }
 
+    catch_interrupt();
+
     for i in 0.. {
+        if interrupted() { break }
+
         let mut domain = domain_builder();
         let mut a = agent.sample_behaviour(&mut rng, domain.emit().state());
 
@@ -131,12 +173,12 @@ fn main() {
 
         if (i+1) % eval_interval == 0 {
             // Run an approximate evaluation:
-            let mut pnls = vec![];
-            let mut drifts = vec![];
-            let mut rewards = vec![];
-            let mut terminal_qs = vec![];
+            let mut pnls = RunningEstimate::new();
+            let mut drifts = RunningEstimate::new();
+            let mut rewards = RunningEstimate::new();
+            let mut terminal_qs = RunningEstimate::new();
 
-            for _ in 0..1000 {
+            for _ in 0..eval_episodes {
                 let mut domain = domain_builder();
                 let mut a = agent.policy.mpa(domain.emit().state());
 
@@ -150,7 +192,7 @@ fn main() {
                     let t = domain.step(a);
 
                     i += 1;
-                    drift_sum += a;
+                    drift_sum += domain.current_drift();
                     reward_sum += t.reward;
 
                     if t.terminated() {
@@ -167,10 +209,10 @@ fn main() {
             }
 
             // Summarise results:
-            let pnl_est = Estimate::from_slice(&pnls);
-            let rwd_est = Estimate::from_slice(&rewards);
-            let inv_est = Estimate::from_slice(&terminal_qs);
-            let dft_est = Estimate::from_slice(&drifts);
+            let pnl_est = pnls.estimate();
+            let rwd_est = rewards.estimate();
+            let inv_est = terminal_qs.estimate();
+            let dft_est = drifts.estimate();
 
             // Log plotting data:
             let critic_est = agent.critic.predict_v(&vec![0.0, 0.0]);
@@ -179,10 +221,11 @@ fn main() {
             let drift_bear = agent.policy.mpa(&vec![0.0, -5.0]);
 
             info!(logger, "evaluation {}", i / eval_interval;
+                "episodes" => eval_episodes,
                 "wealth" => pnl_est,
                 "reward" => rwd_est,
                 "inv_terminal" => inv_est,
-                "drift_mean" => Estimate::from_slice(&drifts),
+                "drift_mean" => dft_est,
                 "critic" => critic_est,
                 "drift_neutral" => drift_neutral,
                 "drift_bull" => drift_bull,
@@ -206,8 +249,18 @@ fn main() {
                 drift_neutral: agent.policy.mpa(&vec![0.0, 0.0]),
                 drift_bull: agent.policy.mpa(&vec![0.0, 5.0]),
                 drift_bear: agent.policy.mpa(&vec![0.0, -5.0]),
-            }).ok();
-            file_logger.flush().ok();
+            })?;
+            file_logger.flush()?;
+
+            agent.save(&spec, format!("{}/checkpoint.json", save_dir))?;
         }
     }
+
+    // Only reached on Ctrl-C, after the last episode completed:
+    file_logger.flush()?;
+    agent.save(&spec, format!("{}/checkpoint.json", save_dir))?;
+
+    info!(logger, "interrupted; saved final checkpoint");
+
+    Ok(())
 }
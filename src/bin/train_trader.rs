@@ -4,33 +4,34 @@ extern crate rand;
 extern crate rsrl;
 #[macro_use]
 extern crate slog;
-extern crate csv;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 
 use mm_arl::{
-    TraderDomain,
-    dynamics::ASDynamics,
-    utils::Estimate,
+    Horizon, TraderDomain,
+    checkpoint::{AgentSpec, DomainSpec, SaveLoad, trader_agent},
+    dynamics::{ASDynamics, BrownianMotion, DomainRandomizer, PoissonRate},
+    experiment::{
+        ExperimentError, WeightsSnapshot,
+        linspace, load_config, policy_grid, validate_save_dir, write_csv, write_json,
+    },
+    records::{RecordFormat, RecordWriter},
+    utils::{ExponentialMovingAverage, RunningEstimate, to_offsets},
 };
-use clap::{App, Arg};
+use clap::{App, Arg, ArgMatches};
+use rand::{Rng, SeedableRng, rngs::StdRng};
 use rsrl::{
     OnlineLearner,
-    control::{Controller, ac::TDAC},
+    control::Controller,
     domains::Domain,
-    fa::{
-        TransformedLFA,
-        linear::{LFA, basis::{Projector, Polynomial}, optim::SGD},
-        transforms::Softplus,
-    },
     logging,
-    policies::{Policy, IPP, gaussian::{self, Gaussian}},
-    prediction::{ValuePredictor, td::TD},
+    policies::Policy,
+    prediction::ValuePredictor,
 };
-use std::f64;
+use std::{f64, fmt, process, str::FromStr, time::{SystemTime, UNIX_EPOCH}};
 
-fn mean(x: [f64; 2]) -> f64 { (x[0] - x[1]) / 2.0 }
+const DT: f64 = 0.005;
 
 #[derive(Debug, Serialize)]
 struct Record {
@@ -38,9 +39,11 @@ struct Record {
 
     pub wealth_mean: f64,
     pub wealth_stddev: f64,
+    pub wealth_mean_ema: f64,
 
     pub reward_mean: f64,
     pub reward_stddev: f64,
+    pub reward_mean_ema: f64,
 
     pub inv_mean: f64,
     pub inv_stddev: f64,
@@ -48,80 +51,362 @@ struct Record {
     pub spread_mean: f64,
     pub spread_stddev: f64,
 
+    pub price_mean: f64,
+    pub price_stddev: f64,
+
+    pub ask_fills_mean: f64,
+    pub ask_fills_stddev: f64,
+
+    pub bid_fills_mean: f64,
+    pub bid_fills_stddev: f64,
+
+    pub clamped_mean: f64,
+    pub clamped_stddev: f64,
+
     pub value_estimate: f64,
     pub rp_neutral: f64,
     pub rp_bull: f64,
     pub rp_bear: f64,
 }
 
+#[derive(Debug, Serialize)]
+struct WeightsRecord {
+    pub episode: usize,
+    pub timestamp: u64,
+
+    pub policy: WeightsSnapshot,
+    pub critic: WeightsSnapshot,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct Config {
+    save_dir: String,
+    eval_interval: usize,
+    eval_episodes: usize,
+    seed: Option<u64>,
+
+    price_dynamics: BrownianMotion,
+    execution_dynamics: PoissonRate,
+    /// Parameter ranges redrawn at the start of each training, but not evaluation, episode.
+    domain_randomization: DomainRandomizer,
+    eta: f64,
+    eta_start: Option<f64>,
+    eta_end: Option<f64>,
+    penalty_warmup: f64,
+    inv_bounds: [f64; 2],
+    /// Pull a side whose fill would carry inventory within this distance of its bound.
+    quote_margin: Option<f64>,
+    squash_actions: bool,
+    normalize_reward: bool,
+    normalize_state: bool,
+    observe_price: bool,
+    observe_wealth: bool,
+    wealth_scale: f64,
+    horizon: Horizon,
+
+    basis_degree: u8,
+    critic_lr: f64,
+    actor_lr: f64,
+    sgd_lr: f64,
+    gamma: f64,
+    pretrain: usize,
+
+    format: RecordFormat,
+    ema_alpha: f64,
+    oracle_gamma: f64,
+    dump_weights: Option<String>,
+    policy_grid: bool,
+    grid_times: usize,
+    grid_invs: usize,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            save_dir: String::new(),
+            eval_interval: 1000,
+            eval_episodes: 1000,
+            seed: None,
+
+            price_dynamics: BrownianMotion::new(DT, 2.0),
+            execution_dynamics: PoissonRate::new(DT, 140.0, 1.5),
+            domain_randomization: DomainRandomizer::default(),
+            eta: 0.0,
+            eta_start: None,
+            eta_end: None,
+            penalty_warmup: 0.0,
+            inv_bounds: [-50.0, 50.0],
+            quote_margin: None,
+            squash_actions: false,
+            normalize_reward: false,
+            normalize_state: false,
+            observe_price: false,
+            observe_wealth: false,
+            wealth_scale: 100.0,
+            horizon: Horizon::default(),
+
+            basis_degree: 3,
+            critic_lr: 0.01,
+            actor_lr: 0.000001,
+            sgd_lr: 1.0,
+            gamma: 1.0,
+            pretrain: 1000,
+
+            format: RecordFormat::Csv,
+            ema_alpha: 0.1,
+            oracle_gamma: 0.1,
+            dump_weights: None,
+            policy_grid: false,
+            grid_times: 11,
+            grid_invs: 21,
+        }
+    }
+}
+
 fn main() {
     let matches = App::new("RL trader")
         .arg(Arg::with_name("save_dir")
                 .index(1)
-                .required(true))
+                .required_unless("config"))
         .arg(Arg::with_name("eval_interval")
                 .index(2)
-                .required(true))
+                .required_unless("config"))
+        .arg(Arg::with_name("config")
+                .long("config")
+                .takes_value(true)
+                .value_name("JSON_FILE")
+                .help("Load the configuration from a JSON file; TOML is not supported"))
+        .arg(Arg::with_name("eval_episodes")
+                .long("eval-episodes")
+                .takes_value(true))
+        .arg(Arg::with_name("seed")
+                .long("seed")
+                .takes_value(true))
         .arg(Arg::with_name("eta")
                 .long("eta")
-                .required(false)
-                .default_value("0.0"))
+                .takes_value(true))
+        .arg(Arg::with_name("eta_start")
+                .long("eta-start")
+                .takes_value(true))
+        .arg(Arg::with_name("eta_end")
+                .long("eta-end")
+                .takes_value(true))
+        .arg(Arg::with_name("penalty_warmup")
+                .long("penalty-warmup")
+                .takes_value(true))
+        .arg(Arg::with_name("format")
+                .long("format")
+                .possible_values(&["csv", "jsonl"])
+                .takes_value(true))
+        .arg(Arg::with_name("quote_margin")
+                .long("quote-margin")
+                .takes_value(true))
+        .arg(Arg::with_name("squash_actions")
+                .long("squash-actions"))
+        .arg(Arg::with_name("normalize_reward")
+                .long("normalize-reward"))
+        .arg(Arg::with_name("normalize_state")
+                .long("normalize-state"))
+        .arg(Arg::with_name("observe_price")
+                .long("observe-price"))
+        .arg(Arg::with_name("observe_wealth")
+                .long("observe-wealth"))
+        .arg(Arg::with_name("pretrain")
+                .long("pretrain")
+                .takes_value(true))
+        .arg(Arg::with_name("no_pretrain")
+                .long("no-pretrain"))
+        .arg(Arg::with_name("ema_alpha")
+                .long("ema-alpha")
+                .takes_value(true))
+        .arg(Arg::with_name("oracle_gamma")
+                .long("oracle-gamma")
+                .takes_value(true))
+        .arg(Arg::with_name("basis_degree")
+                .long("basis-degree")
+                .takes_value(true))
+        .arg(Arg::with_name("critic_lr")
+                .long("critic-lr")
+                .takes_value(true))
+        .arg(Arg::with_name("actor_lr")
+                .long("actor-lr")
+                .takes_value(true))
+        .arg(Arg::with_name("sgd_lr")
+                .long("sgd-lr")
+                .takes_value(true))
+        .arg(Arg::with_name("gamma")
+                .long("gamma")
+                .takes_value(true))
+        .arg(Arg::with_name("dump_weights")
+                .long("dump-weights")
+                .takes_value(true))
+        .arg(Arg::with_name("policy_grid")
+                .long("policy-grid"))
+        .arg(Arg::with_name("grid_times")
+                .long("grid-times")
+                .takes_value(true))
+        .arg(Arg::with_name("grid_invs")
+                .long("grid-invs")
+                .takes_value(true))
         .get_matches();
 
-    let save_dir = matches.value_of("save_dir").unwrap();
-    let eval_interval: usize = matches.value_of("eval_interval").unwrap().parse().unwrap();
-    let eta: f64 = matches.value_of("eta").unwrap().parse().unwrap();
+    let config = matches.value_of("config")
+        .map_or_else(|| Ok(Config::default()), load_config)
+        .and_then(|config| apply_overrides(config, &matches));
 
-    let logger = logging::root(logging::stdout());
-    let mut file_logger = csv::Writer::from_path(format!("{}/results.csv", save_dir)).unwrap();
+    if let Err(e) = config.and_then(run) {
+        eprintln!("Error: {}", e);
 
-    let mut rng = rand::thread_rng();
+        process::exit(1);
+    }
+}
 
-    let domain_builder = || TraderDomain::new(ASDynamics::default(), eta);
+fn parse_flag<T>(name: &str, value: &str) -> Result<T, ExperimentError>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    value.parse().map_err(|e| ExperimentError::InvalidConfig(
+        format!("--{} {}: {}", name.replace('_', "-"), value, e)
+    ))
+}
 
-    // Build basis:
-    let basis = Polynomial::new(2, 3).with_constant();
+// Command-line flags take precedence over values loaded from a config file:
+fn apply_overrides(mut config: Config, matches: &ArgMatches) -> Result<Config, ExperimentError> {
+    macro_rules! parse_into {
+        ($($name:ident),*) => {
+            $(if let Some(v) = matches.value_of(stringify!($name)) {
+                config.$name = parse_flag(stringify!($name), v)?;
+            })*
+        }
+    }
 
-    // Build policy:
-    let policy_rp = Gaussian::new(
-        gaussian::mean::Scalar(LFA::scalar(basis.clone(), SGD(1.0))),
-        gaussian::stddev::Scalar(TransformedLFA::scalar(basis.clone(), Softplus)),
-    );
-    let policy_sp = Gaussian::new(
-        gaussian::mean::Scalar(TransformedLFA::scalar(basis.clone(), Softplus)),
-        gaussian::stddev::Scalar(TransformedLFA::scalar(basis.clone(), Softplus)),
+    parse_into!(
+        save_dir, eval_interval, eval_episodes, eta, penalty_warmup, format, pretrain, ema_alpha,
+        oracle_gamma, basis_degree, critic_lr, actor_lr, sgd_lr, gamma, grid_times, grid_invs
     );
-    let policy = IPP::new(policy_rp, policy_sp);
 
-    // Build critic:
-    let critic = TD::new(
-        LFA::scalar(basis.clone(), SGD(1.0)),
-        0.01,
-        1.0
-    );
+    if let Some(dir) = matches.value_of("dump_weights") {
+        config.dump_weights = Some(dir.to_string());
+    }
 
-    // Build agent:
-    let mut agent = TDAC::new(
-        critic,
-        policy,
-        0.000001,
-        1.0,
-    );
+    if let Some(seed) = matches.value_of("seed") {
+        config.seed = Some(parse_flag("seed", seed)?);
+    }
 
-    fn ua_(a: (f64, f64)) -> [f64; 2] {
-        [
-            a.0 + a.1,
-            a.1 - a.0
-        ]
+    if let Some(eta) = matches.value_of("eta_start") {
+        config.eta_start = Some(parse_flag("eta_start", eta)?);
     }
+    if let Some(eta) = matches.value_of("eta_end") {
+        config.eta_end = Some(parse_flag("eta_end", eta)?);
+    }
+
+    if let Some(margin) = matches.value_of("quote_margin") {
+        config.quote_margin = Some(parse_flag("quote_margin", margin)?);
+    }
+    if matches.is_present("squash_actions") { config.squash_actions = true; }
+    if matches.is_present("normalize_reward") { config.normalize_reward = true; }
+    if matches.is_present("normalize_state") { config.normalize_state = true; }
+    if matches.is_present("observe_price") { config.observe_price = true; }
+    if matches.is_present("observe_wealth") { config.observe_wealth = true; }
+    if matches.is_present("no_pretrain") { config.pretrain = 0; }
+    if matches.is_present("policy_grid") { config.policy_grid = true; }
+
+    Ok(config)
+}
+
+/// Fraction of the terminal penalty in force, ramping linearly to one over the first `warmup`
+/// of training.
+fn penalty_scale(progress: f64, warmup: f64) -> f64 {
+    if warmup > 0.0 { (progress / warmup).min(1.0) } else { 1.0 }
+}
+
+/// Terminal-penalty `eta` interpolated linearly from `start` to `end` over training.
+fn eta_schedule(progress: f64, start: f64, end: f64) -> f64 {
+    start + (end - start) * progress
+}
+
+fn run(config: Config) -> Result<(), ExperimentError> {
+    let Config {
+        save_dir, eval_interval, eval_episodes, seed, price_dynamics, execution_dynamics,
+        domain_randomization, eta,
+        eta_start, eta_end, penalty_warmup, inv_bounds, quote_margin, squash_actions,
+        normalize_reward, normalize_state, observe_price, observe_wealth, wealth_scale, horizon,
+        basis_degree, critic_lr, actor_lr, sgd_lr, gamma, pretrain, format, ema_alpha, oracle_gamma,
+        dump_weights, policy_grid: dump_grid, grid_times, grid_invs,
+    } = config;
+
+    validate_save_dir(&save_dir)?;
+    price_dynamics.noise.validate()?;
+    execution_dynamics.validate()?;
+
+    // Either end of the schedule defaults to the constant `eta`:
+    let eta_start = eta_start.unwrap_or(eta);
+    let eta_end = eta_end.unwrap_or(eta);
+    let eta_at = |progress: f64| {
+        eta_schedule(progress, eta_start, eta_end) * penalty_scale(progress, penalty_warmup)
+    };
+
+    if let Some(ref dir) = dump_weights { validate_save_dir(dir)?; }
+
+    let logger = logging::root(logging::stdout());
+    let mut file_logger = RecordWriter::from_path(
+        format, format!("{}/results.{}", save_dir, format.extension())
+    )?;
+
+    let seed = seed.unwrap_or_else(|| rand::thread_rng().gen());
+
+    info!(logger, "seed"; "seed" => seed);
+
+    if execution_dynamics.saturates() {
+        warn!(logger, "match probabilities saturate at small offsets";
+            "scale" => execution_dynamics.scale);
+    }
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut domain_seeds = StdRng::seed_from_u64(seed.wrapping_add(1));
+    let mut randomizer_rng = StdRng::seed_from_u64(seed.wrapping_add(2));
+
+    // Recorded in the checkpoint, so that evaluation rebuilds the same domain:
+    let domain_spec = DomainSpec {
+        inv_bounds, horizon, squash_actions, quote_margin,
+        normalize_state, observe_price, observe_wealth, wealth_scale,
+    };
+
+    // Training progress in [0, 1] sets the terminal penalty in force:
+    let mut domain_builder = |progress: f64| {
+        let dynamics = ASDynamics::new(
+            DT, 100.0, StdRng::seed_from_u64(domain_seeds.gen()),
+            price_dynamics.clone(), execution_dynamics.clone(),
+        );
+
+        domain_spec.apply(
+            TraderDomain::new(dynamics, eta_at(progress)).with_normalized_reward(normalize_reward)
+        )
+    };
+
+    // Build agent:
+    let spec = AgentSpec {
+        n_inputs: domain_spec.n_inputs(),
+        basis_degree,
+
+        sgd_lr,
+        critic_lr,
+        actor_lr,
+        gamma,
+
+        domain: Some(domain_spec.clone()),
+    };
+    let mut agent = trader_agent(&spec);
 
     // Pre-train value function:
-    for _ in 0..1000 {
-        let mut domain = domain_builder();
+    for _ in 0..pretrain {
+        let mut domain = domain_builder(0.0);
         let mut a = agent.sample_behaviour(&mut rng, domain.emit().state());
 
         loop {
-            let a_ = ua_(a);
+            let a_ = to_offsets(a.0, a.1);
             let t = domain.step(a_);
 
             agent.critic.handle_transition(&t);
@@ -134,25 +419,35 @@ fn main() {
         }
     }
 
+    // Smoothed learning curves, carried across evaluations:
+    let mut wealth_ema = ExponentialMovingAverage::new(ema_alpha);
+    let mut reward_ema = ExponentialMovingAverage::new(ema_alpha);
+
     // Run experiment:
-    for i in 0..(1000*eval_interval) {
+    let n_episodes = 1000 * eval_interval;
+
+    for i in 0..n_episodes {
+        let progress = i as f64 / n_episodes as f64;
+
         // Perform evaluation:
         if i % eval_interval == 0 {
-            let mut pnls = vec![];
-            let mut rewards = vec![];
-            let mut terminal_qs = vec![];
-            let mut average_spread = vec![];
-
-            for _ in 0..1000 {
-                let mut domain = domain_builder();
+            let mut pnls = RunningEstimate::new();
+            let mut rewards = RunningEstimate::new();
+            let mut terminal_qs = RunningEstimate::new();
+            let mut average_spread = RunningEstimate::new();
+            let mut terminal_prices = RunningEstimate::new();
+            let mut ask_fills = RunningEstimate::new();
+            let mut bid_fills = RunningEstimate::new();
+            let mut clamped_actions = RunningEstimate::new();
+
+            for _ in 0..eval_episodes {
+                let mut domain = domain_builder(progress);
                 let mut a = agent.sample_target(&mut rng, domain.emit().state());
 
-                let mut i = 1;
                 let mut reward_sum = 0.0;
-                let mut spread_sum = a.1 * 2.0;
 
                 loop {
-                    let a_ = ua_(a);
+                    let a_ = to_offsets(a.0, a.1);
                     let t = domain.step(a_);
 
                     reward_sum += t.reward;
@@ -161,39 +456,70 @@ fn main() {
                         pnls.push(domain.wealth);
                         rewards.push(reward_sum);
                         terminal_qs.push(domain.inv_terminal);
-                        average_spread.push(spread_sum / i as f64);
+                        average_spread.push(domain.time_weighted_avg_spread());
+                        terminal_prices.push(domain.dynamics.price);
+                        ask_fills.push(domain.asks_filled() as f64);
+                        bid_fills.push(domain.bids_filled() as f64);
+                        clamped_actions.push(domain.clamped_actions() as f64);
 
                         break
                     } else {
                         a = agent.sample_target(&mut rng, t.to.state());
-
-                        i += 1;
-                        spread_sum += a.1 * 2.0;
                     }
                 }
             }
 
             // Summarise results:
-            let pnl_est = Estimate::from_slice(&pnls);
-            let rwd_est = Estimate::from_slice(&rewards);
-            let inv_est = Estimate::from_slice(&terminal_qs);
-            let spd_est = Estimate::from_slice(&average_spread);
+            let pnl_est = pnls.estimate();
+            let rwd_est = rewards.estimate();
+            let inv_est = terminal_qs.estimate();
+            let spd_est = average_spread.estimate();
+            let prc_est = terminal_prices.estimate();
+            let ask_est = ask_fills.estimate();
+            let bid_est = bid_fills.estimate();
+            let clp_est = clamped_actions.estimate();
+
+            let wealth_mean_ema = wealth_ema.update(pnl_est.0);
+            let reward_mean_ema = reward_ema.update(rwd_est.0);
 
             // Log plotting data:
-            let critic_est = agent.critic.predict_v(&vec![0.0, 0.0]);
-            let rp_neutral = mean(ua_(agent.policy.mpa(&vec![0.0, 0.0])));
-            let rp_bull = mean(ua_(agent.policy.mpa(&vec![0.0, 5.0])));
-            let rp_bear = mean(ua_(agent.policy.mpa(&vec![0.0, -5.0])));
+            let probe = domain_builder(progress);
+
+            let critic_est = agent.critic.predict_v(&probe.encode_state(0.0, 0.0));
+            let neutral = agent.policy.mpa(&probe.encode_state(0.0, 0.0));
+            let rp_neutral = neutral.0;
+            let rp_bull = agent.policy.mpa(&probe.encode_state(0.0, 5.0)).0;
+            let rp_bear = agent.policy.mpa(&probe.encode_state(0.0, -5.0)).0;
+
+            // Learned quotes at the start against the one-step optimum:
+            let [ask_neutral, bid_neutral] = to_offsets(neutral.0, neutral.1);
+            let myopic_offset = probe.greedy_myopic_action()[0];
+
+            // Critic error against the certainty equivalent of the analytic AS value:
+            let critic_error = if normalize_reward { None } else {
+                probe.analytic_value(oracle_gamma)
+                    .map(|u| critic_est + (-u).ln() / oracle_gamma)
+            };
 
             info!(logger, "evaluation {}", i / eval_interval;
+                "episodes" => eval_episodes,
+                "eta" => eta_at(progress),
                 "wealth" => pnl_est,
                 "reward" => rwd_est,
                 "inv_terminal" => inv_est,
                 "average_spread" => spd_est,
+                "price_terminal" => prc_est,
+                "ask_fills" => ask_est,
+                "bid_fills" => bid_est,
+                "clamped_actions" => clp_est,
                 "critic" => critic_est,
+                "critic_error" => critic_error,
                 "rp_neutral" => rp_neutral,
                 "rp_bull" => rp_bull,
                 "rp_bear" => rp_bear,
+                "ask_neutral" => ask_neutral,
+                "bid_neutral" => bid_neutral,
+                "myopic_offset" => myopic_offset,
             );
 
             file_logger.serialize(Record {
@@ -201,9 +527,11 @@ fn main() {
 
                 wealth_mean: pnl_est.0,
                 wealth_stddev: pnl_est.1,
+                wealth_mean_ema,
 
                 reward_mean: rwd_est.0,
                 reward_stddev: rwd_est.1,
+                reward_mean_ema,
 
                 inv_mean: inv_est.0,
                 inv_stddev: inv_est.1,
@@ -211,20 +539,64 @@ fn main() {
                 spread_mean: spd_est.0,
                 spread_stddev: spd_est.1,
 
-                value_estimate: agent.critic.predict_v(&vec![0.0, 0.0]),
+                price_mean: prc_est.0,
+                price_stddev: prc_est.1,
+
+                ask_fills_mean: ask_est.0,
+                ask_fills_stddev: ask_est.1,
+
+                bid_fills_mean: bid_est.0,
+                bid_fills_stddev: bid_est.1,
+
+                clamped_mean: clp_est.0,
+                clamped_stddev: clp_est.1,
+
+                value_estimate: critic_est,
                 rp_neutral: rp_neutral,
                 rp_bull: rp_bull,
                 rp_bear: rp_bear,
-            }).ok();
-            file_logger.flush().ok();
+            })?;
+            file_logger.flush()?;
+
+            if dump_grid {
+                let grid = policy_grid(
+                    &linspace(0.0, 1.0, grid_times),
+                    &linspace(inv_bounds[0], inv_bounds[1], grid_invs),
+                    |time, inv| {
+                        let a = agent.policy.mpa(&probe.encode_state(time, inv));
+
+                        to_offsets(a.0, a.1)
+                    },
+                );
+
+                write_csv(format!("{}/policy_grid_{}.csv", save_dir, i), &grid)?;
+            }
+
+            agent.save(&spec, format!("{}/checkpoint.json", save_dir))?;
+
+            if let Some(ref dir) = dump_weights {
+                let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or(0);
+
+                write_json(format!("{}/weights_{}_{}.json", dir, i, timestamp), &WeightsRecord {
+                    episode: i,
+                    timestamp,
+
+                    policy: WeightsSnapshot::of(&agent.policy),
+                    critic: WeightsSnapshot::of(&agent.critic),
+                })?;
+            }
         }
 
         // Train agent for one episode:
-        let mut domain = domain_builder();
+        let mut domain = domain_builder(progress);
+
+        domain_randomization.randomize(&mut domain.dynamics, &mut randomizer_rng);
         let mut a = agent.sample_behaviour(&mut rng, domain.emit().state());
 
         loop {
-            let a_ = ua_(a);
+            let a_ = to_offsets(a.0, a.1);
             let t = domain.step(a_).replace_action(a);
 
             agent.handle_transition(&t);
@@ -238,4 +610,6 @@ fn main() {
 
         OnlineLearner::<Vec<f64>, (f64, f64)>::handle_terminal(&mut agent);
     }
+
+    Ok(())
 }
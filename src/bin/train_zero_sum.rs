@@ -8,125 +8,197 @@ extern crate csv;
 extern crate serde;
 
 use mm_arl::{
+    AdversaryControl, AdversaryObjective,
     ZeroSumDomain,
-    utils::Estimate
+    checkpoint::{AgentSpec, SaveLoad, adversary_agent, trader_agent},
+    experiment::{ExperimentError, catch_interrupt, interrupted, validate_save_dir},
+    utils::{Estimate, RunningEstimate, to_offsets}
 };
 use clap::{App, Arg};
 use rand::thread_rng;
 use rsrl::{
     OnlineLearner,
-    control::{Controller, ac::TDAC},
+    control::Controller,
     domains::Domain,
-    fa::{
-        TransformedLFA,
-        linear::{LFA, basis::{Projector, Polynomial}, optim::SGD},
-        transforms::Softplus,
-    },
     logging,
-    policies::{Policy, Beta, IPP, gaussian::{self, Gaussian}},
-    prediction::{ValuePredictor, td::TD},
+    policies::Policy,
+    prediction::ValuePredictor,
 };
-use std::fs::File;
+use std::{fs::File, process};
+
+struct Config {
+    save_dir: String,
+    eval_interval: usize,
+    eval_episodes: usize,
+    pretrain: usize,
+    objective: AdversaryObjective,
+    control: AdversaryControl,
+    drift_noise: Option<f64>,
+    volatility: f64,
+    basis_degree: u8,
+    adversary_basis_degree: u8,
+}
 
 fn main() {
     let matches = App::new("ZS training")
         .arg(Arg::with_name("eval_interval")
                 .index(1)
                 .required(true))
+        .arg(Arg::with_name("save_dir")
+                .long("save-dir")
+                .takes_value(true)
+                .required(true))
+        .arg(Arg::with_name("eval_episodes")
+                .long("eval-episodes")
+                .required(false)
+                .default_value("1000"))
+        .arg(Arg::with_name("pretrain")
+                .long("pretrain")
+                .required(false)
+                .default_value("1000"))
+        .arg(Arg::with_name("adversary_objective")
+                .long("adversary-objective")
+                .required(false)
+                .possible_values(&["pnl", "variance"])
+                .default_value("pnl"))
+        .arg(Arg::with_name("adversary_control")
+                .long("adversary-control")
+                .required(false)
+                .possible_values(&["drift", "intensity"])
+                .default_value("drift"))
+        .arg(Arg::with_name("observe_drift")
+                .long("observe-drift")
+                .takes_value(true)
+                .value_name("NOISE_STDDEV"))
+        .arg(Arg::with_name("volatility")
+                .long("volatility")
+                .required(false)
+                .default_value("2.0"))
+        .arg(Arg::with_name("basis_degree")
+                .long("basis-degree")
+                .required(false)
+                .default_value("3"))
+        .arg(Arg::with_name("adversary_basis_degree")
+                .long("adversary-basis-degree")
+                .required(false)
+                .default_value("5"))
         .get_matches();
 
-    let eval_interval: usize = matches.value_of("eval_interval").unwrap().parse().unwrap();
+    let objective = match matches.value_of("adversary_objective").unwrap() {
+        "variance" => AdversaryObjective::InventoryVariance,
+        _ => AdversaryObjective::NegatedPnl,
+    };
+    let control = match matches.value_of("adversary_control").unwrap() {
+        "intensity" => AdversaryControl::FillIntensity,
+        _ => AdversaryControl::Drift,
+    };
 
-    let logger = logging::root(logging::stdout());
-    let file_logger = logging::root(logging::file(
-        File::create(format!("/tmp/performance.txt")).expect("Failed to create log file.")
-    ));
+    let config = Config {
+        save_dir: matches.value_of("save_dir").unwrap().to_string(),
+        eval_interval: matches.value_of("eval_interval").unwrap().parse().unwrap(),
+        eval_episodes: matches.value_of("eval_episodes").unwrap().parse().unwrap(),
+        pretrain: matches.value_of("pretrain").unwrap().parse().unwrap(),
+        objective,
+        control,
+        drift_noise: matches.value_of("observe_drift").map(|v| v.parse().unwrap()),
+        volatility: matches.value_of("volatility").unwrap().parse().unwrap(),
+        basis_degree: matches.value_of("basis_degree").unwrap().parse().unwrap(),
+        adversary_basis_degree:
+            matches.value_of("adversary_basis_degree").unwrap().parse().unwrap(),
+    };
 
-    let mut rng = thread_rng();
-    let mut trader = {
-        let basis = Polynomial::new(2, 3).with_constant();
+    if let Err(e) = run(config) {
+        eprintln!("Error: {}", e);
 
-        // Build policy:
-        let policy_a = Gaussian::new(
-            gaussian::mean::Scalar(LFA::scalar(basis.clone(), SGD(1.0))),
-            gaussian::stddev::Scalar(TransformedLFA::scalar(basis.clone(), Softplus)),
-        );
-        let policy_b = Gaussian::new(
-            gaussian::mean::Scalar(TransformedLFA::scalar(basis.clone(), Softplus)),
-            gaussian::stddev::Scalar(TransformedLFA::scalar(basis.clone(), Softplus)),
-        );
-        let policy = IPP::new(policy_a, policy_b);
-
-        // Build critic:
-        let critic = TD::new(LFA::scalar(basis.clone(), SGD(1.0)), 0.01, 1.0);
-
-        // Build agent:
-        TDAC::new(
-            critic,
-            policy,
-            0.00001,
-            1.0,
-        )
-    };
+        process::exit(1);
+    }
+}
 
-    let mut adversary = {
-        let basis = Polynomial::new(2, 5).with_constant();
+fn run(config: Config) -> Result<(), ExperimentError> {
+    let Config {
+        save_dir, eval_interval, eval_episodes, pretrain, objective, control, drift_noise,
+        volatility, basis_degree, adversary_basis_degree,
+    } = config;
 
-        // Build policy:
-        let policy = Beta::new(
-            TransformedLFA::scalar(basis.clone(), Softplus),
-            TransformedLFA::scalar(basis.clone(), Softplus),
-        );
+    validate_save_dir(&save_dir)?;
 
-        // Build critic:
-        let critic = TD::new(LFA::scalar(basis, SGD(1.0)), 0.01, 1.0);
+    let domain_builder = || {
+        let domain = ZeroSumDomain::default()
+            .with_volatility(volatility)
+            .with_objective(objective)
+            .with_control(control);
 
-        // Build agent:
-        TDAC::new(
-            critic,
-            policy,
-            0.1,
-            1.0,
-        )
+        match drift_noise {
+            Some(noise) => domain.with_drift_observation(noise),
+            None => domain,
+        }
     };
 
-    fn ua_(a: (f64, f64)) -> [f64; 2] {
-        [
-            a.0 + a.1,
-            a.1 - a.0
-        ]
-    }
+    let logger = logging::root(logging::stdout());
+    let file_logger = logging::root(logging::file(
+        File::create(format!("{}/performance.txt", save_dir))?
+    ));
+
+    let mut rng = thread_rng();
+    let trader_spec = AgentSpec {
+        n_inputs: if drift_noise.is_some() { 3 } else { 2 },
+        basis_degree,
+        sgd_lr: 1.0,
+        critic_lr: 0.01,
+        actor_lr: 0.00001,
+        gamma: 1.0,
+        domain: None,
+    };
+    let mut trader = trader_agent(&trader_spec);
+
+    let adversary_spec = AgentSpec {
+        n_inputs: 2,
+        basis_degree: adversary_basis_degree,
+        sgd_lr: 1.0,
+        critic_lr: 0.01,
+        actor_lr: 0.1,
+        gamma: 1.0,
+        domain: None,
+    };
+    let mut adversary = adversary_agent(&adversary_spec);
+
+    // Trader actions are (reservation shift, half-spread) pairs:
+    fn ua_(a: (f64, f64)) -> [f64; 2] { to_offsets(a.0, a.1) }
 
     // Pre-train value function:
-    for _ in 0..1000 {
-        let mut domain = ZeroSumDomain::default();
+    for _ in 0..pretrain {
+        let mut domain = domain_builder();
         let mut a = (
             ua_(trader.sample_behaviour(&mut rng, domain.emit().state())),
-            adversary.sample_behaviour(&mut rng, domain.emit().state())
+            adversary.sample_behaviour(&mut rng, domain.adversary_observation().state())
         );
 
         loop {
             let t = domain.step(a);
 
             trader.critic.handle_transition(&t);
-            adversary.critic.handle_transition(&t);
+            adversary.critic.handle_transition(&domain.adversary_transition(t.clone()));
 
             if t.terminated() {
                 break
             } else {
                 a = (
                     ua_(trader.sample_behaviour(&mut rng, domain.emit().state())),
-                    adversary.sample_behaviour(&mut rng, domain.emit().state())
+                    adversary.sample_behaviour(&mut rng, domain.adversary_observation().state())
                 );
             }
         }
     }
 
+    catch_interrupt();
+
     for i in 0.. {
-        let mut domain = ZeroSumDomain::default();
+        if interrupted() { break }
+
+        let mut domain = domain_builder();
         let mut a = (
             ua_(trader.sample_behaviour(&mut rng, domain.emit().state())),
-            adversary.sample_behaviour(&mut rng, domain.emit().state())
+            adversary.sample_behaviour(&mut rng, domain.adversary_observation().state())
         );
 
         loop {
@@ -134,14 +206,14 @@ fn main() {
             let is_terminal = t.terminated();
 
             trader.handle_transition(&t.clone().replace_action((a.0[0], a.0[1])));
-            adversary.handle_transition(&t.replace_action(a.1).negate_reward());
+            adversary.handle_transition(&domain.adversary_transition(t));
 
             if is_terminal {
                 break
             } else {
                 a = (
                     ua_(trader.sample_behaviour(&mut rng, domain.emit().state())),
-                    adversary.sample_behaviour(&mut rng, domain.emit().state())
+                    adversary.sample_behaviour(&mut rng, domain.adversary_observation().state())
                 );
             }
         }
@@ -152,20 +224,18 @@ fn main() {
         if (i+1) % eval_interval == 0 {
             // Run an approximate evaluation:
             let mut pnls = vec![];
-            let mut rewards = vec![];
-            let mut terminal_qs = vec![];
-            let mut average_spread = vec![];
+            let mut rewards = RunningEstimate::new();
+            let mut terminal_qs = RunningEstimate::new();
+            let mut average_spread = RunningEstimate::new();
 
-            for _ in 0..1000 {
-                let mut domain = ZeroSumDomain::default();
+            for _ in 0..eval_episodes {
+                let mut domain = domain_builder();
                 let mut a = (
                     ua_(trader.policy.mpa(domain.emit().state())),
-                    adversary.policy.mpa(domain.emit().state())
+                    adversary.policy.mpa(domain.adversary_observation().state())
                 );
 
-                let mut i = 1;
                 let mut reward_sum = 0.0;
-                let mut spread_sum = a.0[0] + a.0[1];
 
                 loop {
                     let t = domain.step(a);
@@ -176,40 +246,46 @@ fn main() {
                         pnls.push(domain.wealth);
                         rewards.push(reward_sum);
                         terminal_qs.push(domain.inv_terminal);
-                        average_spread.push(spread_sum / i as f64);
+                        average_spread.push(domain.time_weighted_avg_spread());
 
                         break
                     } else {
                         a = (
                             ua_(trader.policy.mpa(domain.emit().state())),
-                            adversary.policy.mpa(domain.emit().state())
+                            adversary.policy.mpa(domain.adversary_observation().state())
                         );
-
-                        i += 1;
-                        spread_sum += a.0[0] + a.0[1];
                     }
                 }
             }
 
             let pnl_est = Estimate::from_slice(&pnls);
-            let reward_est = Estimate::from_slice(&rewards);
+            let reward_est = rewards.estimate();
 
             // Log to stdout:
             info!(logger, "evaluation {}", i / eval_interval;
+                "episodes" => eval_episodes,
                 "wealth" => pnl_est,
                 "reward" => reward_est,
-                "critic" => trader.critic.predict_v(&vec![0.0, 0.0]),
-                "inv_terminal" => Estimate::from_slice(&terminal_qs),
-                "spread" => Estimate::from_slice(&average_spread),
+                "critic" => trader.critic.predict_v(domain_builder().emit().state()),
+                "inv_terminal" => terminal_qs.estimate(),
+                "spread" => average_spread.estimate(),
             );
 
             let performance = Estimate::from_slice(&pnls);
             info!(file_logger, "{},{}", performance.0, performance.1);
 
             let d_logger = logging::root(logging::file(
-                File::create("/tmp/returns.txt").expect("Failed to create log file.")
+                File::create(format!("{}/returns.txt", save_dir))?
             ));
             for x in pnls.iter() { info!(d_logger, "{}", x); }
         }
     }
+
+    // Only reached on Ctrl-C, after the last episode completed:
+    trader.save(&trader_spec, format!("{}/trader_checkpoint.json", save_dir))?;
+    adversary.save(&adversary_spec, format!("{}/adversary_checkpoint.json", save_dir))?;
+
+    info!(logger, "interrupted; saved final checkpoints");
+
+    Ok(())
 }
@@ -0,0 +1,408 @@
+//! Crate-local checkpoint format for the agents trained by the binaries.
+//!
+//! An agent is stored as the spec needed to rebuild it plus the plain weight vectors of each of
+//! its approximators, in a fixed order. Only this module knows how the `rsrl` types are put
+//! together, so a change in their layout or serialisation is confined to it.
+use crate::{
+    Horizon, TraderDomain,
+    dynamics::{ExecutionDynamics, PriceDynamics},
+    experiment::ExperimentError,
+    records::{from_json, to_json},
+};
+use rsrl::{
+    control::ac::TDAC,
+    fa::{
+        Parameterised,
+        TransformedLFA,
+        linear::{
+            LFA, ScalarFunction,
+            basis::{Projector, Polynomial, Constant, Stacker},
+            optim::SGD,
+        },
+        transforms::Softplus,
+    },
+    policies::{Beta, IPP, gaussian::{self, Gaussian}},
+    prediction::td::TD,
+};
+use rand::Rng;
+use std::{fs, path::Path};
+
+const FORMAT_VERSION: u32 = 1;
+
+pub type Basis = Stacker<Polynomial, Constant>;
+
+type Linear = LFA<Basis, SGD, ScalarFunction>;
+type Positive = TransformedLFA<Basis, ScalarFunction, Softplus>;
+
+pub type TraderPolicy = IPP<
+    Gaussian<gaussian::mean::Scalar<Linear>, gaussian::stddev::Scalar<Positive>>,
+    Gaussian<gaussian::mean::Scalar<Positive>, gaussian::stddev::Scalar<Positive>>,
+>;
+
+/// Gaussian actor-critic over (reservation shift, half-spread), as in `train_trader`.
+pub type TraderAgent = TDAC<TD<Linear>, TraderPolicy>;
+
+/// Beta actor-critic over the normalised drift, as in `train_adversary`.
+pub type AdversaryAgent = TDAC<TD<Linear>, Beta<Positive, Positive>>;
+
+/// Everything besides the weights needed to rebuild an agent.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AgentSpec {
+    pub n_inputs: usize,
+    pub basis_degree: u8,
+
+    pub sgd_lr: f64,
+    pub critic_lr: f64,
+    pub actor_lr: f64,
+    pub gamma: f64,
+
+    /// The trader domain the agent was trained on; absent for adversaries.
+    #[serde(default)]
+    pub domain: Option<DomainSpec>,
+}
+
+impl AgentSpec {
+    pub fn basis(&self) -> Basis {
+        Polynomial::new(self.n_inputs, self.basis_degree).with_constant()
+    }
+
+    /// The recorded trader domain, checked against the number of inputs the agent reads.
+    pub fn trader_domain(&self) -> Result<&DomainSpec, ExperimentError> {
+        let domain = self.domain.as_ref().ok_or_else(|| ExperimentError::InvalidCheckpoint(
+            "no trader domain recorded".to_string()
+        ))?;
+
+        if domain.n_inputs() != self.n_inputs {
+            return Err(ExperimentError::InvalidCheckpoint(format!(
+                "the recorded domain emits {} inputs, but the agent reads {}",
+                domain.n_inputs(), self.n_inputs
+            )));
+        }
+
+        Ok(domain)
+    }
+}
+
+/// Settings of a `TraderDomain` that change what the agent observes or how its actions are
+/// applied, so that evaluation rebuilds the MDP it was trained on.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DomainSpec {
+    pub inv_bounds: [f64; 2],
+    pub horizon: Horizon,
+    pub squash_actions: bool,
+    pub quote_margin: Option<f64>,
+
+    pub normalize_state: bool,
+    pub observe_price: bool,
+    pub observe_wealth: bool,
+    pub wealth_scale: f64,
+}
+
+impl DomainSpec {
+    pub fn n_inputs(&self) -> usize {
+        2 + self.observe_price as usize + self.observe_wealth as usize
+    }
+
+    pub fn apply<P, E, R>(&self, domain: TraderDomain<P, E, R>) -> TraderDomain<P, E, R>
+    where
+        P: PriceDynamics,
+        E: ExecutionDynamics,
+        R: Rng,
+    {
+        let domain = match self.quote_margin {
+            Some(margin) => domain.with_quote_constraints(margin),
+            None => domain,
+        };
+
+        domain
+            .with_inv_bounds(self.inv_bounds)
+            .with_horizon(self.horizon)
+            .with_squashed_actions(self.squash_actions)
+            .with_normalized_state(self.normalize_state)
+            .with_price_observation(self.observe_price)
+            .with_wealth_observation(self.observe_wealth, self.wealth_scale)
+    }
+}
+
+pub fn trader_agent(spec: &AgentSpec) -> TraderAgent {
+    let basis = spec.basis();
+
+    let policy = IPP::new(
+        Gaussian::new(
+            gaussian::mean::Scalar(LFA::scalar(basis.clone(), SGD(spec.sgd_lr))),
+            gaussian::stddev::Scalar(TransformedLFA::scalar(basis.clone(), Softplus)),
+        ),
+        Gaussian::new(
+            gaussian::mean::Scalar(TransformedLFA::scalar(basis.clone(), Softplus)),
+            gaussian::stddev::Scalar(TransformedLFA::scalar(basis.clone(), Softplus)),
+        ),
+    );
+    let critic = TD::new(LFA::scalar(basis, SGD(spec.sgd_lr)), spec.critic_lr, spec.gamma);
+
+    TDAC::new(critic, policy, spec.actor_lr, spec.gamma)
+}
+
+pub fn adversary_agent(spec: &AgentSpec) -> AdversaryAgent {
+    let basis = spec.basis();
+
+    let policy = Beta::new(
+        TransformedLFA::scalar(basis.clone(), Softplus),
+        TransformedLFA::scalar(basis.clone(), Softplus),
+    );
+    let critic = TD::new(LFA::scalar(basis, SGD(spec.sgd_lr)), spec.critic_lr, spec.gamma);
+
+    TDAC::new(critic, policy, spec.actor_lr, spec.gamma)
+}
+
+/// Agents that round-trip through the checkpoint format.
+pub trait SaveLoad: Sized {
+    fn to_bytes(&self, spec: &AgentSpec) -> Result<Vec<u8>, ExperimentError>;
+
+    fn from_bytes(bytes: &[u8]) -> Result<(AgentSpec, Self), ExperimentError>;
+
+    fn save<P: AsRef<Path>>(&self, spec: &AgentSpec, path: P) -> Result<(), ExperimentError> {
+        fs::write(path, self.to_bytes(spec)?)?;
+
+        Ok(())
+    }
+
+    fn load<P: AsRef<Path>>(path: P) -> Result<(AgentSpec, Self), ExperimentError> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    version: u32,
+    kind: String,
+    spec: AgentSpec,
+    weights: Vec<Vec<f64>>,
+}
+
+fn read<T: Parameterised + ?Sized>(leaf: &T) -> Vec<f64> {
+    leaf.weights_view().iter().cloned().collect()
+}
+
+fn write<T: Parameterised + ?Sized>(leaf: &mut T, weights: &[f64]) -> Result<(), ExperimentError> {
+    let [rows, cols] = leaf.weights_dim();
+
+    if rows * cols != weights.len() {
+        return Err(ExperimentError::WeightsShape {
+            expected: [rows, cols],
+            found: [weights.len(), 1],
+        });
+    }
+
+    for (w, &v) in leaf.weights_view_mut().iter_mut().zip(weights.iter()) { *w = v; }
+
+    Ok(())
+}
+
+fn encode(kind: &str, spec: &AgentSpec, weights: Vec<Vec<f64>>)
+    -> Result<Vec<u8>, ExperimentError>
+{
+    // JSON has no non-finite numbers, so they would be written as nulls that fail to decode:
+    for (i, leaf) in weights.iter().enumerate() {
+        if let Some(w) = leaf.iter().find(|w| !w.is_finite()) {
+            return Err(ExperimentError::InvalidCheckpoint(
+                format!("weight vector {} holds the non-finite value {}", i, w)
+            ));
+        }
+    }
+
+    let snapshot = Snapshot {
+        version: FORMAT_VERSION,
+        kind: kind.to_string(),
+        spec: spec.clone(),
+        weights,
+    };
+
+    Ok(to_json(&snapshot)?.into_bytes())
+}
+
+fn decode(kind: &str, n_leaves: usize, bytes: &[u8]) -> Result<Snapshot, ExperimentError> {
+    let text = String::from_utf8_lossy(bytes);
+    let snapshot: Snapshot = from_json(&text)?;
+
+    if snapshot.version != FORMAT_VERSION {
+        return Err(ExperimentError::InvalidCheckpoint(
+            format!("unsupported format version {}", snapshot.version)
+        ));
+    }
+
+    if snapshot.kind != kind {
+        return Err(ExperimentError::InvalidCheckpoint(
+            format!("expected a checkpoint of kind {}, found {}", kind, snapshot.kind)
+        ));
+    }
+
+    if snapshot.weights.len() != n_leaves {
+        return Err(ExperimentError::InvalidCheckpoint(
+            format!("expected {} weight vectors, found {}", n_leaves, snapshot.weights.len())
+        ));
+    }
+
+    Ok(snapshot)
+}
+
+// Leaves: critic, then each Gaussian's mean and stddev, reservation shift first.
+impl SaveLoad for TraderAgent {
+    fn to_bytes(&self, spec: &AgentSpec) -> Result<Vec<u8>, ExperimentError> {
+        encode("trader", spec, vec![
+            read(&self.critic.v_func),
+            read(&self.policy.0.mean.0),
+            read(&self.policy.0.stddev.0),
+            read(&self.policy.1.mean.0),
+            read(&self.policy.1.stddev.0),
+        ])
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<(AgentSpec, Self), ExperimentError> {
+        let Snapshot { spec, weights, .. } = decode("trader", 5, bytes)?;
+        let mut agent = trader_agent(&spec);
+
+        write(&mut agent.critic.v_func, &weights[0])?;
+        write(&mut agent.policy.0.mean.0, &weights[1])?;
+        write(&mut agent.policy.0.stddev.0, &weights[2])?;
+        write(&mut agent.policy.1.mean.0, &weights[3])?;
+        write(&mut agent.policy.1.stddev.0, &weights[4])?;
+
+        Ok((spec, agent))
+    }
+}
+
+// Leaves: critic, then the Beta's alpha and beta.
+impl SaveLoad for AdversaryAgent {
+    fn to_bytes(&self, spec: &AgentSpec) -> Result<Vec<u8>, ExperimentError> {
+        encode("adversary", spec, vec![
+            read(&self.critic.v_func),
+            read(&self.policy.alpha),
+            read(&self.policy.beta),
+        ])
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<(AgentSpec, Self), ExperimentError> {
+        let Snapshot { spec, weights, .. } = decode("adversary", 3, bytes)?;
+        let mut agent = adversary_agent(&spec);
+
+        write(&mut agent.critic.v_func, &weights[0])?;
+        write(&mut agent.policy.alpha, &weights[1])?;
+        write(&mut agent.policy.beta, &weights[2])?;
+
+        Ok((spec, agent))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trader_spec() -> AgentSpec {
+        AgentSpec {
+            n_inputs: 3,
+            basis_degree: 1,
+            sgd_lr: 0.1,
+            critic_lr: 0.1,
+            actor_lr: 0.01,
+            gamma: 1.0,
+            domain: Some(DomainSpec {
+                inv_bounds: [-10.0, 20.0],
+                horizon: Horizon::Uniform(0.5, 1.0),
+                squash_actions: true,
+                quote_margin: Some(1.0),
+                normalize_state: true,
+                observe_price: true,
+                observe_wealth: false,
+                wealth_scale: 100.0,
+            }),
+        }
+    }
+
+    fn adversary_spec() -> AgentSpec {
+        AgentSpec { n_inputs: 2, domain: None, ..trader_spec() }
+    }
+
+    // Distinct weights per leaf and per entry, so that a swapped or shifted leaf is caught:
+    fn scramble<T: Parameterised + ?Sized>(leaf: &mut T, offset: f64) {
+        for (i, w) in leaf.weights_view_mut().iter_mut().enumerate() {
+            *w = offset + 0.1 * i as f64 - 0.37;
+        }
+    }
+
+    #[test]
+    fn trader_weights_survive_a_round_trip() {
+        let spec = trader_spec();
+        let mut agent = trader_agent(&spec);
+
+        scramble(&mut agent.critic.v_func, 1.0);
+        scramble(&mut agent.policy.0.mean.0, 2.0);
+        scramble(&mut agent.policy.0.stddev.0, 3.0);
+        scramble(&mut agent.policy.1.mean.0, 4.0);
+        scramble(&mut agent.policy.1.stddev.0, 5.0);
+
+        let bytes = agent.to_bytes(&spec).unwrap();
+        let (loaded_spec, loaded) = TraderAgent::from_bytes(&bytes).unwrap();
+
+        assert_eq!(loaded_spec, spec);
+        assert_eq!(read(&loaded.critic.v_func), read(&agent.critic.v_func));
+        assert_eq!(read(&loaded.policy.0.mean.0), read(&agent.policy.0.mean.0));
+        assert_eq!(read(&loaded.policy.0.stddev.0), read(&agent.policy.0.stddev.0));
+        assert_eq!(read(&loaded.policy.1.mean.0), read(&agent.policy.1.mean.0));
+        assert_eq!(read(&loaded.policy.1.stddev.0), read(&agent.policy.1.stddev.0));
+    }
+
+    #[test]
+    fn adversary_weights_survive_a_round_trip() {
+        let spec = adversary_spec();
+        let mut agent = adversary_agent(&spec);
+
+        scramble(&mut agent.critic.v_func, 1.0);
+        scramble(&mut agent.policy.alpha, 2.0);
+        scramble(&mut agent.policy.beta, 3.0);
+
+        let bytes = agent.to_bytes(&spec).unwrap();
+        let (loaded_spec, loaded) = AdversaryAgent::from_bytes(&bytes).unwrap();
+
+        assert_eq!(loaded_spec, spec);
+        assert_eq!(read(&loaded.critic.v_func), read(&agent.critic.v_func));
+        assert_eq!(read(&loaded.policy.alpha), read(&agent.policy.alpha));
+        assert_eq!(read(&loaded.policy.beta), read(&agent.policy.beta));
+
+        // The kind is checked, so an adversary never loads as a trader:
+        assert!(TraderAgent::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn non_finite_weights_are_rejected_on_save() {
+        let spec = adversary_spec();
+
+        for &bad in &[f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            let mut agent = adversary_agent(&spec);
+
+            agent.policy.beta.weights_view_mut()[[0, 0]] = bad;
+
+            assert!(agent.to_bytes(&spec).is_err());
+        }
+    }
+
+    #[test]
+    fn trader_domain_survives_a_round_trip() {
+        let spec = trader_spec();
+        let bytes = trader_agent(&spec).to_bytes(&spec).unwrap();
+        let (loaded, _) = TraderAgent::from_bytes(&bytes).unwrap();
+
+        assert_eq!(loaded, spec);
+        assert_eq!(loaded.trader_domain().unwrap(), spec.domain.as_ref().unwrap());
+    }
+
+    #[test]
+    fn trader_domain_must_be_recorded_and_match_the_inputs() {
+        let mut spec = trader_spec();
+
+        spec.n_inputs = 4;
+        assert!(spec.trader_domain().is_err());
+
+        spec.domain = None;
+        assert!(spec.trader_domain().is_err());
+    }
+}
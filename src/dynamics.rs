@@ -1,29 +1,128 @@
-use rand::{Rng, rngs::ThreadRng, thread_rng};
-use rand_distr::StandardNormal;
+use rand::{Rng, SeedableRng, distributions::Distribution, rngs::{StdRng, ThreadRng}, thread_rng};
+use rand_distr::{StandardNormal, StudentT};
+use std::{cell::Cell, collections::VecDeque, error, fmt};
+
+// Number of standard deviations used to bound a single price increment.
+const INCREMENT_SIGMAS: f64 = 5.0;
+
+// Search range and resolution for offsets without a closed-form myopic optimum.
+const MYOPIC_SEARCH_LIMIT: f64 = 100.0;
+const MYOPIC_GRID_POINTS: usize = 1000;
 
 pub trait ExecutionDynamics {
     fn match_prob(&self, offset: f64) -> f64;
+
+    /// Match probability for an order resting at `time`; stationary models ignore the time.
+    fn match_prob_at(&self, _time: f64, offset: f64) -> f64 { self.match_prob(offset) }
+
+    /// Largest offset at which a fill remains plausible.
+    fn max_offset(&self) -> f64 { f64::INFINITY }
+
+    /// Offset maximising the one-step expected spread capture, `o * match_prob_at(time, o)`.
+    ///
+    /// By default this is a grid search over `[0, max_offset]`, capped at 100 price units, and
+    /// so is only as fine as that grid.
+    fn myopic_offset(&self, time: f64) -> f64 {
+        let upper = self.max_offset().min(MYOPIC_SEARCH_LIMIT);
+        let capture = |o: f64| o * self.match_prob_at(time, o);
+
+        (0..=MYOPIC_GRID_POINTS)
+            .map(|i| upper * i as f64 / MYOPIC_GRID_POINTS as f64)
+            .fold((0.0, 0.0), |best, o| {
+                let c = capture(o);
+
+                if c > best.1 { (o, c) } else { best }
+            })
+            .0
+    }
+
+    /// Offset actually achieved by a fill of an order quoted at `offset`.
+    fn realized_offset<R: Rng>(&self, offset: f64, _rng: &mut R) -> f64 { offset }
+
+    /// Advance any latent state by one time step.
+    fn on_step<R: Rng>(&mut self, _rng: &mut R) {}
+
+    fn reset(&mut self) {}
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PoissonRate {
     dt: f64,
     pub scale: f64,
     pub decay: f64,
 }
 
+/// Parameters for which a `PoissonRate` is no longer a sensible fill model.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InvalidRate {
+    NegativeScale(f64),
+    /// Fills would not fall off, or would grow more likely, the further an order rests from the
+    /// mid.
+    NonPositiveDecay(f64),
+    NonPositiveDt(f64),
+}
+
+impl fmt::Display for InvalidRate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InvalidRate::NegativeScale(scale) => write!(f, "negative arrival scale {}", scale),
+            InvalidRate::NonPositiveDecay(decay) =>
+                write!(f, "non-positive intensity decay {}", decay),
+            InvalidRate::NonPositiveDt(dt) => write!(f, "non-positive time step {}", dt),
+        }
+    }
+}
+
+impl error::Error for InvalidRate {}
+
 impl PoissonRate {
     pub fn new(dt: f64, scale: f64, decay: f64) -> PoissonRate {
         PoissonRate { dt, scale, decay, }
     }
+
+    /// As `new`, but checked with `validate`.
+    pub fn try_new(dt: f64, scale: f64, decay: f64) -> Result<PoissonRate, InvalidRate> {
+        let rate = PoissonRate::new(dt, scale, decay);
+
+        rate.validate()?;
+
+        Ok(rate)
+    }
+
+    /// Reject invalid parameters, e.g. of a rate deserialised from a config file.
+    ///
+    /// A rate that `saturates` is valid, so callers that care should check for it separately.
+    pub fn validate(&self) -> Result<(), InvalidRate> {
+        if self.dt <= 0.0 {
+            return Err(InvalidRate::NonPositiveDt(self.dt));
+        } else if self.scale < 0.0 {
+            return Err(InvalidRate::NegativeScale(self.scale));
+        } else if self.decay <= 0.0 {
+            return Err(InvalidRate::NonPositiveDecay(self.decay));
+        }
+
+        Ok(())
+    }
+
+    /// Whether `scale * dt > 1`, in which case `match_prob` is clamped to one for all offsets up
+    /// to `ln(scale * dt) / decay`, and the fill model loses its sensitivity there.
+    pub fn saturates(&self) -> bool { self.scale * self.dt > 1.0 }
 }
 
 impl ExecutionDynamics for PoissonRate {
     fn match_prob(&self, offset: f64) -> f64 {
         let lambda = self.scale * (-self.decay * offset).exp();
 
-        (lambda * self.dt).max(0.0).min(1.0)
+        (lambda * self.dt).clamp(0.0, 1.0)
+    }
+
+    // Beyond this offset the match probability falls below machine precision:
+    fn max_offset(&self) -> f64 {
+        (self.scale * self.dt / f64::EPSILON).ln() / self.decay
     }
+
+    // The maximiser of o exp(-k o), provided lambda dt is not clamped there:
+    fn myopic_offset(&self, _: f64) -> f64 { 1.0 / self.decay }
 }
 
 impl Default for PoissonRate {
@@ -32,28 +131,449 @@ impl Default for PoissonRate {
     }
 }
 
+/// Fills every order with the same probability, regardless of its offset.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ConstantRate {
+    pub prob: f64,
+}
+
+impl ConstantRate {
+    pub fn new(prob: f64) -> ConstantRate {
+        ConstantRate { prob, }
+    }
+}
+
+impl ExecutionDynamics for ConstantRate {
+    fn match_prob(&self, _: f64) -> f64 { self.prob }
+}
+
+/// Empirical fill curve: match probabilities at increasing offsets, linearly interpolated between
+/// breakpoints and held flat beyond the first and last.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TabulatedRate {
+    offsets: Vec<f64>,
+    probs: Vec<f64>,
+}
+
+impl TabulatedRate {
+    /// Build from `(offset, fill_probability)` pairs, which must be non-empty, strictly
+    /// increasing in offset and have probabilities in `[0, 1]`.
+    pub fn from_pairs(pairs: Vec<(f64, f64)>) -> TabulatedRate {
+        assert!(!pairs.is_empty(), "a fill-rate table needs at least one breakpoint");
+        assert!(
+            pairs.windows(2).all(|w| w[0].0 < w[1].0),
+            "fill-rate offsets must be strictly increasing"
+        );
+        assert!(
+            pairs.iter().all(|&(_, p)| (0.0..=1.0).contains(&p)),
+            "fill probabilities must lie in [0, 1]"
+        );
+
+        let (offsets, probs) = pairs.into_iter().unzip();
+
+        TabulatedRate { offsets, probs, }
+    }
+}
+
+impl ExecutionDynamics for TabulatedRate {
+    fn match_prob(&self, offset: f64) -> f64 {
+        let n = self.offsets.len();
+        let i = self.offsets.iter().position(|&o| o > offset).unwrap_or(n);
+
+        match i {
+            0 => self.probs[0],
+            i if i == n => self.probs[n - 1],
+            i => {
+                let (o0, p0) = (self.offsets[i - 1], self.probs[i - 1]);
+                let (o1, p1) = (self.offsets[i], self.probs[i]);
+
+                p0 + (p1 - p0) * (offset - o0) / (o1 - o0)
+            },
+        }
+    }
+
+    // The first breakpoint from which the table is zero throughout, if there is one:
+    fn max_offset(&self) -> f64 {
+        let n_zero = self.probs.iter().rev().take_while(|&&p| p == 0.0).count();
+
+        if n_zero == 0 { f64::INFINITY } else { self.offsets[self.offsets.len() - n_zero] }
+    }
+}
+
+/// A probability outside `[0, 1]`, or NaN, which `Rng::gen_bool` would panic on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InvalidProbability(pub f64);
+
+impl fmt::Display for InvalidProbability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "probability {} is not in [0, 1]", self.0)
+    }
+}
+
+impl error::Error for InvalidProbability {}
+
+fn check_probability(p: f64) -> Result<(), InvalidProbability> {
+    if (0.0..=1.0).contains(&p) { Ok(()) } else { Err(InvalidProbability(p)) }
+}
+
+/// Fills that, with probability `slippage_prob`, execute `slippage` worse than quoted.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WithSlippage<E> {
+    pub inner: E,
+    pub slippage_prob: f64,
+    pub slippage: f64,
+}
+
+impl<E> WithSlippage<E> {
+    /// Panics if `slippage_prob` is not a probability; see `try_new`.
+    pub fn new(inner: E, slippage_prob: f64, slippage: f64) -> WithSlippage<E> {
+        match WithSlippage::try_new(inner, slippage_prob, slippage) {
+            Ok(dynamics) => dynamics,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    pub fn try_new(inner: E, slippage_prob: f64,
+                   slippage: f64) -> Result<WithSlippage<E>, InvalidProbability>
+    {
+        let dynamics = WithSlippage { inner, slippage_prob, slippage, };
+
+        dynamics.validate()?;
+
+        Ok(dynamics)
+    }
+
+    /// Reject a `slippage_prob` outside `[0, 1]`, e.g. of dynamics deserialised from a config.
+    pub fn validate(&self) -> Result<(), InvalidProbability> {
+        check_probability(self.slippage_prob)
+    }
+}
+
+impl<E: ExecutionDynamics> ExecutionDynamics for WithSlippage<E> {
+    fn match_prob(&self, offset: f64) -> f64 { self.inner.match_prob(offset) }
+
+    fn match_prob_at(&self, time: f64, offset: f64) -> f64 {
+        self.inner.match_prob_at(time, offset)
+    }
+
+    fn max_offset(&self) -> f64 { self.inner.max_offset() }
+
+    fn myopic_offset(&self, time: f64) -> f64 { self.inner.myopic_offset(time) }
+
+    fn realized_offset<R: Rng>(&self, offset: f64, rng: &mut R) -> f64 {
+        let offset = self.inner.realized_offset(offset, rng);
+
+        if rng.gen_bool(self.slippage_prob) {
+            offset - self.slippage
+        } else {
+            offset
+        }
+    }
+
+    fn on_step<R: Rng>(&mut self, rng: &mut R) { self.inner.on_step(rng) }
+
+    fn reset(&mut self) { self.inner.reset() }
+}
+
+/// Fills that, with probability `improvement_prob`, execute `improvement` better than quoted, as
+/// when a large aggressor sweeps through the resting order's level.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WithPriceImprovement<E> {
+    pub inner: E,
+    pub improvement_prob: f64,
+    pub improvement: f64,
+}
+
+impl<E> WithPriceImprovement<E> {
+    /// Panics if `improvement_prob` is not a probability; see `try_new`.
+    pub fn new(inner: E, improvement_prob: f64, improvement: f64) -> WithPriceImprovement<E> {
+        match WithPriceImprovement::try_new(inner, improvement_prob, improvement) {
+            Ok(dynamics) => dynamics,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    pub fn try_new(inner: E, improvement_prob: f64,
+                   improvement: f64) -> Result<WithPriceImprovement<E>, InvalidProbability>
+    {
+        let dynamics = WithPriceImprovement { inner, improvement_prob, improvement, };
+
+        dynamics.validate()?;
+
+        Ok(dynamics)
+    }
+
+    /// Reject an `improvement_prob` outside `[0, 1]`, e.g. of dynamics deserialised from a config.
+    pub fn validate(&self) -> Result<(), InvalidProbability> {
+        check_probability(self.improvement_prob)
+    }
+}
+
+impl<E: ExecutionDynamics> ExecutionDynamics for WithPriceImprovement<E> {
+    fn match_prob(&self, offset: f64) -> f64 { self.inner.match_prob(offset) }
+
+    fn match_prob_at(&self, time: f64, offset: f64) -> f64 {
+        self.inner.match_prob_at(time, offset)
+    }
+
+    fn max_offset(&self) -> f64 { self.inner.max_offset() }
+
+    fn myopic_offset(&self, time: f64) -> f64 { self.inner.myopic_offset(time) }
+
+    fn realized_offset<R: Rng>(&self, offset: f64, rng: &mut R) -> f64 {
+        let offset = self.inner.realized_offset(offset, rng);
+
+        if rng.gen_bool(self.improvement_prob) {
+            offset + self.improvement
+        } else {
+            offset
+        }
+    }
+
+    fn on_step<R: Rng>(&mut self, rng: &mut R) { self.inner.on_step(rng) }
+
+    fn reset(&mut self) { self.inner.reset() }
+}
+
+/// Time-of-day multiplier applied to an execution intensity.
+pub trait Schedule {
+    fn factor(&self, time: f64) -> f64;
+}
+
+impl<F: Fn(f64) -> f64> Schedule for F {
+    fn factor(&self, time: f64) -> f64 { self(time) }
+}
+
+/// A schedule breakpoint, `(time, value)`, with a NaN or infinite entry.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NonFiniteBreakpoint(pub f64, pub f64);
+
+impl fmt::Display for NonFiniteBreakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "breakpoint ({}, {}) is not finite", self.0, self.1)
+    }
+}
+
+impl error::Error for NonFiniteBreakpoint {}
+
+// Sort breakpoints by time, once every entry is known to be finite:
+fn sorted_breakpoints(
+    mut breakpoints: Vec<(f64, f64)>,
+) -> Result<Vec<(f64, f64)>, NonFiniteBreakpoint> {
+    if let Some(&(t, v)) = breakpoints.iter().find(|(t, v)| !t.is_finite() || !v.is_finite()) {
+        return Err(NonFiniteBreakpoint(t, v));
+    }
+
+    breakpoints.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    Ok(breakpoints)
+}
+
+/// Linear interpolation between `(time, factor)` knots, held flat beyond the first and last.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PiecewiseLinear {
+    pub knots: Vec<(f64, f64)>,
+}
+
+impl PiecewiseLinear {
+    pub fn new(knots: Vec<(f64, f64)>) -> Result<PiecewiseLinear, NonFiniteBreakpoint> {
+        Ok(PiecewiseLinear { knots: sorted_breakpoints(knots)?, })
+    }
+}
+
+impl Schedule for PiecewiseLinear {
+    fn factor(&self, time: f64) -> f64 {
+        let i = self.knots.iter().position(|&(t, _)| t > time).unwrap_or(self.knots.len());
+
+        match (i, self.knots.len()) {
+            (_, 0) => 1.0,
+            (0, _) => self.knots[0].1,
+            (i, n) if i == n => self.knots[n - 1].1,
+            (i, _) => {
+                let (t0, f0) = self.knots[i - 1];
+                let (t1, f1) = self.knots[i];
+
+                f0 + (f1 - f0) * (time - t0) / (t1 - t0)
+            },
+        }
+    }
+}
+
+/// Scales the inner model's match probability by `schedule.factor(time)`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScheduledRate<E, S = PiecewiseLinear> {
+    pub inner: E,
+    pub schedule: S,
+}
+
+impl<E, S> ScheduledRate<E, S> {
+    pub fn new(inner: E, schedule: S) -> ScheduledRate<E, S> {
+        ScheduledRate { inner, schedule, }
+    }
+}
+
+impl<E: ExecutionDynamics, S: Schedule> ExecutionDynamics for ScheduledRate<E, S> {
+    // Without a time, the unscheduled baseline is reported:
+    fn match_prob(&self, offset: f64) -> f64 { self.inner.match_prob(offset) }
+
+    fn match_prob_at(&self, time: f64, offset: f64) -> f64 {
+        (self.inner.match_prob_at(time, offset) * self.schedule.factor(time)).clamp(0.0, 1.0)
+    }
+
+    fn max_offset(&self) -> f64 { self.inner.max_offset() }
+
+    fn myopic_offset(&self, time: f64) -> f64 { self.inner.myopic_offset(time) }
+
+    fn realized_offset<R: Rng>(&self, offset: f64, rng: &mut R) -> f64 {
+        self.inner.realized_offset(offset, rng)
+    }
+
+    fn on_step<R: Rng>(&mut self, rng: &mut R) { self.inner.on_step(rng) }
+
+    fn reset(&mut self) { self.inner.reset() }
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub enum NoiseKind {
+    #[default]
+    Gaussian,
+
+    // Rescaled to unit variance, hence `dof` must exceed 2.
+    StudentT { dof: f64 },
+}
+
+/// Student-t noise with too few degrees of freedom to be rescaled to unit variance.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InvalidDof(pub f64);
+
+impl fmt::Display for InvalidDof {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Student-t noise needs more than 2 degrees of freedom, found {}", self.0)
+    }
+}
+
+impl error::Error for InvalidDof {}
+
+impl NoiseKind {
+    pub fn student_t(dof: f64) -> Result<NoiseKind, InvalidDof> {
+        let noise = NoiseKind::StudentT { dof };
+
+        noise.validate()?;
+
+        Ok(noise)
+    }
+
+    /// Reject a `StudentT` built directly or deserialised with `dof <= 2`, whose samples would
+    /// be NaN or panic.
+    pub fn validate(&self) -> Result<(), InvalidDof> {
+        match *self {
+            NoiseKind::StudentT { dof } if dof <= 2.0 || dof.is_nan() => Err(InvalidDof(dof)),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> f64 {
+        match *self {
+            NoiseKind::Gaussian => rng.sample(StandardNormal),
+            NoiseKind::StudentT { dof } => {
+                let t: f64 = rng.sample(StudentT::new(dof).expect("Invalid degrees of freedom."));
+
+                t * ((dof - 2.0) / dof).sqrt()
+            },
+        }
+    }
+}
+
+/// Distribution from which the starting price of each episode is drawn.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum PriceDistribution {
+    Uniform { low: f64, high: f64 },
+    Normal { mean: f64, stddev: f64 },
+}
+
+impl PriceDistribution {
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> f64 {
+        match *self {
+            PriceDistribution::Uniform { low, high } => rng.gen_range(low, high),
+            PriceDistribution::Normal { mean, stddev } => {
+                let z: f64 = rng.sample(StandardNormal);
+
+                mean + stddev * z
+            },
+        }
+    }
+}
+
 pub trait PriceDynamics {
     fn sample_increment<R: Rng>(&self, rng: &mut R, x: f64) -> f64;
+
+    /// The `n` prices that follow `x0`, drawn as successive increments.
+    fn sample_path<R: Rng>(&self, rng: &mut R, x0: f64, n: usize) -> Vec<f64> {
+        let mut x = x0;
+
+        (0..n).map(|_| {
+            x += self.sample_increment(rng, x);
+
+            x
+        }).collect()
+    }
+
+    fn volatility(&self) -> f64;
+
+    /// Conditional mean of the next increment, `E[dP | x]`, given the current price `x`.
+    fn expected_increment(&self, _x: f64) -> f64 { 0.0 }
+
+    /// Deviation of the observed price, against which orders fill, from the fundamental price.
+    fn sample_observation_noise<R: Rng>(&self, _rng: &mut R) -> f64 { 0.0 }
+
+    /// Advance any latent state by one time step, before the next observation is drawn.
+    fn on_step<R: Rng>(&mut self, _rng: &mut R) {}
+
+    /// Notification that one of the trader's orders filled: `+1` for a buy, `-1` for a sell.
+    fn on_fill(&mut self, _side: f64) {}
+
+    fn reset(&mut self) {}
+
+    fn is_exhausted(&self) -> bool { false }
+}
+
+/// Price models whose drift can be set between steps, e.g. by an adversary.
+pub trait SettableDrift {
+    fn drift(&self) -> f64;
+
+    fn set_drift(&mut self, d: f64);
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct BrownianMotion {
     dt: f64,
     pub volatility: f64,
+    #[serde(default)]
+    pub noise: NoiseKind,
 }
 
 impl BrownianMotion {
     pub fn new(dt: f64, volatility: f64) -> BrownianMotion {
-        BrownianMotion { dt, volatility, }
+        BrownianMotion { dt, volatility, noise: NoiseKind::Gaussian, }
+    }
+
+    pub fn with_noise(mut self, noise: NoiseKind) -> Self {
+        if let Err(e) = noise.validate() { panic!("{}", e); }
+
+        self.noise = noise;
+
+        self
     }
 }
 
 impl PriceDynamics for BrownianMotion {
     fn sample_increment<R: Rng>(&self, rng: &mut R, _: f64) -> f64 {
-        let w: f64 = rng.sample(StandardNormal);
+        let w = self.noise.sample(rng);
 
         self.volatility * self.dt.sqrt() * w
     }
+
+    fn volatility(&self) -> f64 { self.volatility }
 }
 
 impl Default for BrownianMotion {
@@ -62,25 +582,45 @@ impl Default for BrownianMotion {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct BrownianMotionWithDrift {
     dt: f64,
     pub drift: f64,
     pub volatility: f64,
+    #[serde(default)]
+    pub noise: NoiseKind,
 }
 
 impl BrownianMotionWithDrift {
     pub fn new(dt: f64, drift: f64, volatility: f64) -> BrownianMotionWithDrift {
-        BrownianMotionWithDrift { dt, drift, volatility, }
+        BrownianMotionWithDrift { dt, drift, volatility, noise: NoiseKind::Gaussian, }
+    }
+
+    pub fn with_noise(mut self, noise: NoiseKind) -> Self {
+        if let Err(e) = noise.validate() { panic!("{}", e); }
+
+        self.noise = noise;
+
+        self
     }
 }
 
 impl PriceDynamics for BrownianMotionWithDrift {
     fn sample_increment<R: Rng>(&self, rng: &mut R, _: f64) -> f64 {
-        let w: f64 = rng.sample(StandardNormal);
+        let w = self.noise.sample(rng);
 
         self.drift * self.dt + self.volatility * self.dt.sqrt() * w
     }
+
+    fn volatility(&self) -> f64 { self.volatility }
+
+    fn expected_increment(&self, _: f64) -> f64 { self.drift * self.dt }
+}
+
+impl SettableDrift for BrownianMotionWithDrift {
+    fn drift(&self) -> f64 { self.drift }
+
+    fn set_drift(&mut self, d: f64) { self.drift = d; }
 }
 
 impl Default for BrownianMotionWithDrift {
@@ -89,7 +629,71 @@ impl Default for BrownianMotionWithDrift {
     }
 }
 
-#[derive(Debug)]
+/// Brownian motion whose drift follows a fixed schedule of `(time_threshold, drift)` breakpoints.
+///
+/// The drift in force is that of the last breakpoint whose threshold has been reached, and zero
+/// before the first. `sample_increment` is not told the time, so the process tracks it internally,
+/// advancing by `dt` in `on_step`; it must be reset along with the dynamics that own it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScheduledDriftBrownianMotion {
+    dt: f64,
+    pub breakpoints: Vec<(f64, f64)>,
+    pub volatility: f64,
+    #[serde(default)]
+    pub noise: NoiseKind,
+
+    #[serde(skip)]
+    time: f64,
+}
+
+impl ScheduledDriftBrownianMotion {
+    pub fn new(dt: f64, breakpoints: Vec<(f64, f64)>,
+               volatility: f64) -> Result<ScheduledDriftBrownianMotion, NonFiniteBreakpoint>
+    {
+        Ok(ScheduledDriftBrownianMotion {
+            dt, volatility,
+            breakpoints: sorted_breakpoints(breakpoints)?,
+
+            noise: NoiseKind::Gaussian,
+            time: 0.0,
+        })
+    }
+
+    pub fn with_noise(mut self, noise: NoiseKind) -> Self {
+        if let Err(e) = noise.validate() { panic!("{}", e); }
+
+        self.noise = noise;
+
+        self
+    }
+
+    /// Drift in force for the next increment.
+    pub fn current_drift(&self) -> f64 {
+        self.breakpoints.iter()
+            .take_while(|&&(threshold, _)| threshold <= self.time)
+            .last()
+            .map_or(0.0, |&(_, drift)| drift)
+    }
+}
+
+impl PriceDynamics for ScheduledDriftBrownianMotion {
+    fn sample_increment<R: Rng>(&self, rng: &mut R, _: f64) -> f64 {
+        let drift = self.current_drift();
+        let w = self.noise.sample(rng);
+
+        drift * self.dt + self.volatility * self.dt.sqrt() * w
+    }
+
+    fn volatility(&self) -> f64 { self.volatility }
+
+    fn expected_increment(&self, _: f64) -> f64 { self.current_drift() * self.dt }
+
+    fn on_step<R: Rng>(&mut self, _rng: &mut R) { self.time += self.dt; }
+
+    fn reset(&mut self) { self.time = 0.0; }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct OrnsteinUhlenbeck {
     dt: f64,
     pub rate: f64,
@@ -108,15 +712,19 @@ impl PriceDynamics for OrnsteinUhlenbeck {
 
         -self.rate * x * self.dt + w.sample_increment(rng, x)
     }
+
+    fn volatility(&self) -> f64 { self.volatility }
+
+    fn expected_increment(&self, x: f64) -> f64 { -self.rate * x * self.dt }
 }
 
 impl Default for OrnsteinUhlenbeck {
     fn default() -> OrnsteinUhlenbeck {
-        OrnsteinUhlenbeck::new(1.0, 1.0, 1.0)
+        OrnsteinUhlenbeck::new(0.005, 1.0, 1.0)
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct OrnsteinUhlenbeckWithDrift {
     dt: f64,
     pub rate: f64,
@@ -136,29 +744,379 @@ impl PriceDynamics for OrnsteinUhlenbeckWithDrift {
 
         self.rate * (self.drift - x) * self.dt + w.sample_increment(rng, x)
     }
+
+    fn volatility(&self) -> f64 { self.volatility }
+
+    fn expected_increment(&self, x: f64) -> f64 { self.rate * (self.drift - x) * self.dt }
+}
+
+// The drift of this model is the level to which it reverts:
+impl SettableDrift for OrnsteinUhlenbeckWithDrift {
+    fn drift(&self) -> f64 { self.drift }
+
+    fn set_drift(&mut self, d: f64) { self.drift = d; }
 }
 
 impl Default for OrnsteinUhlenbeckWithDrift {
     fn default() -> OrnsteinUhlenbeckWithDrift {
-        OrnsteinUhlenbeckWithDrift::new(1.0, 1.0, 0.0, 1.0)
+        OrnsteinUhlenbeckWithDrift::new(0.005, 1.0, 0.0, 1.0)
     }
 }
 
-#[derive(Debug)]
-pub struct ASDynamics<P, E> {
-    rng: ThreadRng,
+/// Superposition of two independent price processes, each driven by the same current price.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Sum<A, B> {
+    pub a: A,
+    pub b: B,
+}
+
+impl<A, B> Sum<A, B> {
+    pub fn new(a: A, b: B) -> Sum<A, B> {
+        Sum { a, b, }
+    }
+}
+
+impl<A: PriceDynamics, B: PriceDynamics> PriceDynamics for Sum<A, B> {
+    fn sample_increment<R: Rng>(&self, rng: &mut R, x: f64) -> f64 {
+        self.a.sample_increment(rng, x) + self.b.sample_increment(rng, x)
+    }
+
+    fn volatility(&self) -> f64 { self.a.volatility().hypot(self.b.volatility()) }
+
+    fn expected_increment(&self, x: f64) -> f64 {
+        self.a.expected_increment(x) + self.b.expected_increment(x)
+    }
+
+    fn sample_observation_noise<R: Rng>(&self, rng: &mut R) -> f64 {
+        self.a.sample_observation_noise(rng) + self.b.sample_observation_noise(rng)
+    }
+
+    fn on_step<R: Rng>(&mut self, rng: &mut R) {
+        self.a.on_step(rng);
+        self.b.on_step(rng);
+    }
+
+    fn on_fill(&mut self, side: f64) {
+        self.a.on_fill(side);
+        self.b.on_fill(side);
+    }
+
+    fn reset(&mut self) {
+        self.a.reset();
+        self.b.reset();
+    }
+
+    fn is_exhausted(&self) -> bool { self.a.is_exhausted() || self.b.is_exhausted() }
+}
+
+/// Roll-model bid-ask bounce: the observed price sits `half_spread` above or below the inner
+/// process's fundamental price, on either side with equal probability and independently each
+/// step, so successive observed increments are negatively autocorrelated.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MicrostructureNoise<P> {
+    pub inner: P,
+    pub half_spread: f64,
+}
+
+impl<P> MicrostructureNoise<P> {
+    pub fn new(inner: P, half_spread: f64) -> MicrostructureNoise<P> {
+        MicrostructureNoise { inner, half_spread, }
+    }
+}
+
+impl<P: PriceDynamics> PriceDynamics for MicrostructureNoise<P> {
+    fn sample_increment<R: Rng>(&self, rng: &mut R, x: f64) -> f64 {
+        self.inner.sample_increment(rng, x)
+    }
+
+    fn volatility(&self) -> f64 { self.inner.volatility() }
+
+    fn expected_increment(&self, x: f64) -> f64 { self.inner.expected_increment(x) }
+
+    fn sample_observation_noise<R: Rng>(&self, rng: &mut R) -> f64 {
+        let bounce = if rng.gen_bool(0.5) { self.half_spread } else { -self.half_spread };
+
+        self.inner.sample_observation_noise(rng) + bounce
+    }
+
+    fn on_step<R: Rng>(&mut self, rng: &mut R) { self.inner.on_step(rng) }
+
+    fn on_fill(&mut self, side: f64) { self.inner.on_fill(side) }
+
+    fn reset(&mut self) { self.inner.reset() }
+
+    fn is_exhausted(&self) -> bool { self.inner.is_exhausted() }
+}
+
+/// Transient impact on top of the inner process, which remains the fundamental price.
+///
+/// Each of the trader's fills displaces the observed price by `impact` in the direction of the
+/// trade, and the total displacement decays at `decay_rate` per unit time. Being observation
+/// noise, it moves the price that later orders fill against but not the mark-to-market.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TransientImpact<P> {
+    dt: f64,
+    pub inner: P,
+    pub impact: f64,
+    pub decay_rate: f64,
+
+    #[serde(skip)]
+    displacement: f64,
+}
+
+impl<P> TransientImpact<P> {
+    pub fn new(dt: f64, inner: P, impact: f64, decay_rate: f64) -> TransientImpact<P> {
+        TransientImpact { dt, inner, impact, decay_rate, displacement: 0.0, }
+    }
+
+    pub fn displacement(&self) -> f64 { self.displacement }
+}
+
+impl<P: PriceDynamics> PriceDynamics for TransientImpact<P> {
+    fn sample_increment<R: Rng>(&self, rng: &mut R, x: f64) -> f64 {
+        self.inner.sample_increment(rng, x)
+    }
+
+    fn volatility(&self) -> f64 { self.inner.volatility() }
+
+    fn expected_increment(&self, x: f64) -> f64 { self.inner.expected_increment(x) }
+
+    fn sample_observation_noise<R: Rng>(&self, rng: &mut R) -> f64 {
+        self.inner.sample_observation_noise(rng) + self.displacement
+    }
+
+    fn on_step<R: Rng>(&mut self, rng: &mut R) {
+        self.inner.on_step(rng);
+        self.displacement *= (-self.decay_rate * self.dt).exp();
+    }
+
+    fn on_fill(&mut self, side: f64) {
+        self.inner.on_fill(side);
+        self.displacement += side * self.impact;
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+        self.displacement = 0.0;
+    }
+
+    fn is_exhausted(&self) -> bool { self.inner.is_exhausted() }
+}
+
+impl<P: SettableDrift> SettableDrift for TransientImpact<P> {
+    fn drift(&self) -> f64 { self.inner.drift() }
+
+    fn set_drift(&mut self, d: f64) { self.inner.set_drift(d) }
+}
+
+impl<P: SettableDrift> SettableDrift for MicrostructureNoise<P> {
+    fn drift(&self) -> f64 { self.inner.drift() }
+
+    fn set_drift(&mut self, d: f64) { self.inner.set_drift(d) }
+}
+
+/// Exponential fill intensity whose scale mean-reverts as an Ornstein-Uhlenbeck process.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MeanRevertingRate {
+    dt: f64,
+    pub decay: f64,
+    pub scale_process: OrnsteinUhlenbeckWithDrift,
+
+    scale: f64,
+}
+
+impl MeanRevertingRate {
+    pub fn new(dt: f64, decay: f64, scale_process: OrnsteinUhlenbeckWithDrift) -> MeanRevertingRate {
+        let scale = scale_process.drift;
+
+        MeanRevertingRate { dt, decay, scale_process, scale, }
+    }
+
+    pub fn current_scale(&self) -> f64 { self.scale }
+}
+
+impl ExecutionDynamics for MeanRevertingRate {
+    fn match_prob(&self, offset: f64) -> f64 {
+        let lambda = self.scale.max(0.0) * (-self.decay * offset).exp();
+
+        (lambda * self.dt).clamp(0.0, 1.0)
+    }
+
+    fn max_offset(&self) -> f64 {
+        (self.scale.max(f64::EPSILON) * self.dt / f64::EPSILON).ln() / self.decay
+    }
+
+    fn myopic_offset(&self, _: f64) -> f64 { 1.0 / self.decay }
+
+    fn on_step<R: Rng>(&mut self, rng: &mut R) {
+        self.scale += self.scale_process.sample_increment(rng, self.scale);
+    }
+
+    fn reset(&mut self) { self.scale = self.scale_process.drift; }
+}
+
+impl Default for MeanRevertingRate {
+    // Fluctuates by roughly 2% about the default `PoissonRate` scale.
+    fn default() -> MeanRevertingRate {
+        MeanRevertingRate::new(0.005, 1.5, OrnsteinUhlenbeckWithDrift::new(0.005, 5.0, 140.0, 10.0))
+    }
+}
+
+// Lower-triangular Cholesky factor of a dense, symmetric positive-definite matrix.
+fn cholesky(a: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    let n = a.len();
+    let mut l = vec![vec![0.0; n]; n];
+
+    for i in 0..n {
+        for j in 0..=i {
+            let s: f64 = (0..j).map(|k| l[i][k] * l[j][k]).sum();
+
+            l[i][j] = if i == j {
+                (a[i][i] - s).max(0.0).sqrt()
+            } else {
+                (a[i][j] - s) / l[j][j]
+            };
+        }
+    }
+
+    l
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PrecomputedGaussianPath {
+    dt: f64,
+    pub length_scale: f64,
+    pub amplitude: f64,
+
+    increments: Vec<f64>,
+    #[serde(skip)]
+    cursor: Cell<usize>,
+}
+
+impl PrecomputedGaussianPath {
+    pub fn new<R: Rng>(length_scale: f64, amplitude: f64, dt: f64, horizon: f64,
+                       rng: &mut R) -> PrecomputedGaussianPath
+    {
+        let n = (horizon / dt).ceil() as usize + 1;
+        let a2 = amplitude * amplitude;
+
+        // Squared-exponential kernel, with a little jitter for numerical stability:
+        let kernel: Vec<Vec<f64>> = (0..n).map(|i| (0..n).map(|j| {
+            let d = (i as f64 - j as f64) * dt;
+            let k = a2 * (-d * d / (2.0 * length_scale * length_scale)).exp();
+
+            if i == j { k + 1e-9 * a2 } else { k }
+        }).collect()).collect();
+
+        let l = cholesky(&kernel);
+        let z: Vec<f64> = (0..n).map(|_| rng.sample(StandardNormal)).collect();
+        let path: Vec<f64> = l.iter().map(|row| {
+            row.iter().zip(z.iter()).map(|(lij, zj)| lij * zj).sum()
+        }).collect();
+
+        PrecomputedGaussianPath {
+            dt, length_scale, amplitude,
+
+            increments: path.windows(2).map(|w| w[1] - w[0]).collect(),
+            cursor: Cell::new(0),
+        }
+    }
+}
+
+impl PriceDynamics for PrecomputedGaussianPath {
+    fn sample_increment<R: Rng>(&self, _: &mut R, _: f64) -> f64 {
+        let i = self.cursor.get();
+
+        match self.increments.get(i) {
+            Some(inc) => {
+                self.cursor.set(i + 1);
+
+                *inc
+            },
+            None => 0.0,
+        }
+    }
+
+    fn sample_path<R: Rng>(&self, _: &mut R, x0: f64, n: usize) -> Vec<f64> {
+        let i = self.cursor.get().min(self.increments.len());
+        let j = (i + n).min(self.increments.len());
+
+        self.cursor.set(j);
+
+        // Once the stored path runs out the price holds, as with `sample_increment`:
+        let mut x = x0;
+        let mut path: Vec<f64> = self.increments[i..j].iter()
+            .map(|inc| { x += inc; x })
+            .collect();
+
+        path.resize(n, x);
+        path
+    }
+
+    // Per-step increment variance is 2a^2 (1 - exp(-dt^2 / 2l^2)):
+    fn volatility(&self) -> f64 {
+        let r = self.dt / self.length_scale;
+        let var = 2.0 * self.amplitude.powi(2) * (1.0 - (-r * r / 2.0).exp());
+
+        (var / self.dt).sqrt()
+    }
+
+    // Replays the same path from the start; construct a new instance to draw a fresh one.
+    fn reset(&mut self) { self.cursor.set(0); }
+
+    fn is_exhausted(&self) -> bool { self.cursor.get() >= self.increments.len() }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ASDynamics<P, E, R = ThreadRng> {
+    // Only the model parameters are persisted; deserialized instances draw from a fresh RNG.
+    #[serde(skip)]
+    rng: R,
 
     pub dt: f64,
     pub time: f64,
     pub price: f64,
     pub price_initial: f64,
+    /// Redraws `price_initial` on every reset when set; otherwise the start price is fixed.
+    #[serde(default)]
+    pub price_distribution: Option<PriceDistribution>,
 
     pub price_dynamics: P,
     pub execution_dynamics: E,
+
+    pub queue_delay: usize,
+    /// Fill exactly those orders whose match probability is at least one half.
+    #[serde(default)]
+    pub deterministic_fills: bool,
+
+    #[serde(skip)]
+    step: usize,
+    #[serde(skip)]
+    price_noise: f64,
+    #[serde(skip)]
+    pending_asks: VecDeque<(usize, f64)>,
+    #[serde(skip)]
+    pending_bids: VecDeque<(usize, f64)>,
 }
 
-impl<P, E> ASDynamics<P, E> {
-    pub fn new(dt: f64, price: f64, rng: ThreadRng,
+pub type SeededASDynamics<P, E> = ASDynamics<P, E, StdRng>;
+
+// Pop the order that becomes eligible to fill at `step`, discarding any stale orders that missed
+// their chance.
+fn take_eligible(queue: &mut VecDeque<(usize, f64)>, step: usize, delay: usize) -> Option<f64> {
+    while let Some(&(placed, price)) = queue.front() {
+        if placed + delay > step { return None; }
+
+        queue.pop_front();
+
+        if placed + delay == step { return Some(price); }
+    }
+
+    None
+}
+
+impl<P, E, R> ASDynamics<P, E, R> {
+    pub fn new(dt: f64, price: f64, rng: R,
                price_dynamics: P, execution_dynamics: E) -> Self
     {
         ASDynamics {
@@ -168,11 +1126,38 @@ impl<P, E> ASDynamics<P, E> {
             time: 0.0,
             price,
             price_initial: price,
+            price_distribution: None,
 
             price_dynamics,
             execution_dynamics,
+
+            queue_delay: 0,
+            deterministic_fills: false,
+
+            step: 0,
+            price_noise: 0.0,
+            pending_asks: VecDeque::new(),
+            pending_bids: VecDeque::new(),
         }
     }
+
+    pub fn with_queue_delay(mut self, queue_delay: usize) -> Self {
+        self.queue_delay = queue_delay;
+
+        self
+    }
+
+    pub fn with_deterministic_fills(mut self, deterministic_fills: bool) -> Self {
+        self.deterministic_fills = deterministic_fills;
+
+        self
+    }
+}
+
+impl<P, E, R: SeedableRng> ASDynamics<P, E, R> {
+    /// Replace the RNG with one seeded from `seed`, e.g. to split a cloned rollout from its
+    /// original; the rest of the state is untouched.
+    pub fn reseed(&mut self, seed: u64) { self.rng = R::seed_from_u64(seed); }
 }
 
 impl ASDynamics<BrownianMotionWithDrift, PoissonRate> {
@@ -197,41 +1182,403 @@ impl Default for ASDynamics<BrownianMotion, PoissonRate> {
     }
 }
 
-impl<P, E> ASDynamics<P, E>
+impl ASDynamics<BrownianMotion, PoissonRate, StdRng> {
+    /// Default dynamics driven by `StdRng::seed_from_u64(seed)`.
+    ///
+    /// Episodes are reproducible bit-for-bit for a given seed, so any change to the number or
+    /// order of RNG draws per step alters results. `StdRng` is only stable within a `rand`
+    /// release, and transcendental functions may round differently across platforms.
+    pub fn seeded(seed: u64) -> Self {
+        const DT: f64 = 0.005;
+
+        let pd = BrownianMotion::new(DT, 2.0);
+        let ed = PoissonRate::new(DT, 140.0, 1.5);
+
+        ASDynamics::new(DT, 100.0, StdRng::seed_from_u64(seed), pd, ed)
+    }
+}
+
+/// Ranges from which to redraw the default model's parameters, uniformly, for domain
+/// randomisation; parameters without a range are left as they are.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DomainRandomizer {
+    pub volatility: Option<[f64; 2]>,
+    pub scale: Option<[f64; 2]>,
+    pub decay: Option<[f64; 2]>,
+}
+
+impl DomainRandomizer {
+    /// Redraw the configured parameters of `dynamics`, e.g. at the start of an episode.
+    ///
+    /// Draws come from `rng` rather than the dynamics' own RNG, so its price path is unchanged.
+    pub fn randomize<Q, R>(&self, dynamics: &mut ASDynamics<BrownianMotion, PoissonRate, Q>,
+                           rng: &mut R)
+    where
+        R: Rng,
+    {
+        let mut draw = |range: Option<[f64; 2]>, value: &mut f64| {
+            if let Some([low, high]) = range {
+                *value = if low < high { rng.gen_range(low, high) } else { low };
+            }
+        };
+
+        draw(self.volatility, &mut dynamics.price_dynamics.volatility);
+        draw(self.scale, &mut dynamics.execution_dynamics.scale);
+        draw(self.decay, &mut dynamics.execution_dynamics.decay);
+    }
+}
+
+impl<P, E, R> ASDynamics<P, E, R>
 where
     P: PriceDynamics,
     E: ExecutionDynamics,
+    R: Rng,
 {
+    pub fn with_price_distribution(mut self, price_distribution: PriceDistribution) -> Self {
+        self.price_distribution = Some(price_distribution);
+        self.price_initial = price_distribution.sample(&mut self.rng);
+        self.price = self.price_initial;
+
+        self
+    }
+
+    pub fn reset(&mut self) {
+        if let Some(price_distribution) = self.price_distribution {
+            self.price_initial = price_distribution.sample(&mut self.rng);
+        }
+
+        self.time = 0.0;
+        self.price = self.price_initial;
+        self.price_dynamics.reset();
+        self.execution_dynamics.reset();
+
+        self.step = 0;
+        self.price_noise = 0.0;
+        self.pending_asks.clear();
+        self.pending_bids.clear();
+    }
+
+    /// Conditional mean of the next price increment from the current price.
+    pub fn expected_increment(&self) -> f64 {
+        self.price_dynamics.expected_increment(self.price)
+    }
+
+    /// Bound on the magnitude of a driftless price increment over one step.
+    pub fn increment_bound(&self) -> f64 {
+        INCREMENT_SIGMAS * self.price_dynamics.volatility() * self.dt.sqrt()
+    }
+
     pub fn innovate(&mut self) -> f64 {
-        let mut rng = thread_rng();
+        let price_inc = self.price_dynamics.sample_increment(&mut self.rng, self.price);
 
-        let price_inc = self.price_dynamics.sample_increment(&mut rng, self.price);
+        self.execution_dynamics.on_step(&mut self.rng);
+        self.price_dynamics.on_step(&mut self.rng);
 
+        self.step += 1;
         self.time += self.dt;
         self.price += price_inc;
+        self.price_noise = self.price_dynamics.sample_observation_noise(&mut self.rng);
 
         price_inc
     }
 
+    /// Price against which orders fill; `price` remains the fundamental used to mark to market.
+    pub fn observed_price(&self) -> f64 { self.price + self.price_noise }
+
+    /// Standard normal draw from the dynamics' own RNG, keeping auxiliary noise seeded.
+    pub fn sample_standard_normal(&mut self) -> f64 { self.rng.sample(StandardNormal) }
+
+    /// Draw from any distribution using the dynamics' own RNG.
+    pub fn sample<T, D: Distribution<T>>(&mut self, distribution: &D) -> T {
+        self.rng.sample(distribution)
+    }
+
     fn try_execute(&mut self, offset: f64) -> Option<f64> {
-        let match_prob = self.execution_dynamics.match_prob(offset);
+        let match_prob = self.execution_dynamics.match_prob_at(self.time, offset);
+        let filled = if self.deterministic_fills {
+            match_prob >= 0.5
+        } else {
+            self.rng.gen_bool(match_prob)
+        };
 
-        if self.rng.gen_bool(match_prob) {
-            Some(offset)
+        if filled {
+            Some(self.execution_dynamics.realized_offset(offset, &mut self.rng))
         } else {
             None
         }
     }
 
+    // Orders join the queue at the current step and are matched, at their original price, only
+    // once `queue_delay` steps have elapsed. Matching is against the observed price, but the
+    // returned offset is relative to the current fundamental price.
     pub fn try_execute_ask(&mut self, order_price: f64) -> Option<f64> {
-        let offset = order_price - self.price;
+        self.pending_asks.push_back((self.step, order_price));
+
+        let noise = self.price_noise;
 
-        self.try_execute(offset)
+        let fill = take_eligible(&mut self.pending_asks, self.step, self.queue_delay)
+            .and_then(|p| self.try_execute(p - self.observed_price()))
+            .map(|offset| offset + noise);
+
+        if fill.is_some() { self.price_dynamics.on_fill(-1.0); }
+
+        fill
     }
 
     pub fn try_execute_bid(&mut self, order_price: f64) -> Option<f64> {
-        let offset = self.price - order_price;
+        self.pending_bids.push_back((self.step, order_price));
+
+        let noise = self.price_noise;
+
+        let fill = take_eligible(&mut self.pending_bids, self.step, self.queue_delay)
+            .and_then(|p| self.try_execute(self.observed_price() - p))
+            .map(|offset| offset - noise);
+
+        if fill.is_some() { self.price_dynamics.on_fill(1.0); }
+
+        fill
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const N_SAMPLES: usize = 200_000;
+
+    // Sample mean, variance and excess kurtosis:
+    fn moments(xs: &[f64]) -> [f64; 3] {
+        let n = xs.len() as f64;
+        let mean = xs.iter().sum::<f64>() / n;
+        let central = |p: i32| xs.iter().map(|x| (x - mean).powi(p)).sum::<f64>() / n;
+        let var = central(2);
+
+        [mean, var, central(4) / (var * var) - 3.0]
+    }
+
+    fn noise_samples(noise: NoiseKind) -> Vec<f64> {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        (0..N_SAMPLES).map(|_| noise.sample(&mut rng)).collect()
+    }
+
+    #[test]
+    fn student_t_noise_has_unit_variance_and_fat_tails() {
+        let [_, gauss_var, gauss_kurt] = moments(&noise_samples(NoiseKind::Gaussian));
+        let [_, t_var, t_kurt] = moments(&noise_samples(NoiseKind::student_t(5.0).unwrap()));
+
+        assert!((gauss_var - 1.0).abs() < 0.02);
+        assert!((t_var - 1.0).abs() < 0.05);
+
+        // The excess kurtosis of a t-distribution is 6 / (dof - 4), here 6:
+        assert!(gauss_kurt.abs() < 0.1);
+        assert!(t_kurt > 2.0);
+    }
+
+    #[test]
+    fn student_t_needs_more_than_two_dof() {
+        assert_eq!(NoiseKind::student_t(2.0).unwrap_err(), InvalidDof(2.0));
+        assert_eq!(NoiseKind::student_t(-1.0).unwrap_err(), InvalidDof(-1.0));
+        assert!(NoiseKind::StudentT { dof: f64::NAN }.validate().is_err());
+        assert!(NoiseKind::Gaussian.validate().is_ok());
+    }
+
+    #[test]
+    fn poisson_rate_rejects_invalid_parameters() {
+        assert!(PoissonRate::try_new(0.005, 140.0, 1.5).is_ok());
+        assert!(PoissonRate::try_new(0.005, 0.0, 1.5).is_ok());
+
+        assert_eq!(PoissonRate::try_new(0.0, 140.0, 1.5).unwrap_err(),
+                   InvalidRate::NonPositiveDt(0.0));
+        assert_eq!(PoissonRate::try_new(0.005, -1.0, 1.5).unwrap_err(),
+                   InvalidRate::NegativeScale(-1.0));
+        assert_eq!(PoissonRate::try_new(0.005, 140.0, 0.0).unwrap_err(),
+                   InvalidRate::NonPositiveDecay(0.0));
+        assert_eq!(PoissonRate::try_new(0.005, 140.0, -1.5).unwrap_err(),
+                   InvalidRate::NonPositiveDecay(-1.5));
+    }
+
+    #[test]
+    fn saturating_poisson_rate_is_valid() {
+        let rate = PoissonRate::try_new(0.005, 400.0, 1.5).unwrap();
+
+        assert!(rate.saturates());
+        assert!(!PoissonRate::default().saturates());
+
+        // Clamped to one up to ln(scale * dt) / decay:
+        assert_eq!(rate.match_prob(2f64.ln() / 1.5 - 1e-9), 1.0);
+        assert!(rate.match_prob(2f64.ln() / 1.5 + 1e-3) < 1.0);
+    }
+
+    #[test]
+    fn slippage_needs_a_probability() {
+        assert!(WithSlippage::try_new(ConstantRate::new(1.0), 0.5, 0.1).is_ok());
+        assert!(WithSlippage::try_new(ConstantRate::new(1.0), 1.0, 0.1).is_ok());
+        assert_eq!(WithSlippage::try_new(ConstantRate::new(1.0), 1.5, 0.1).unwrap_err(),
+                   InvalidProbability(1.5));
+        assert!(WithSlippage::try_new(ConstantRate::new(1.0), -0.1, 0.1).is_err());
+        assert!(WithSlippage::try_new(ConstantRate::new(1.0), f64::NAN, 0.1).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "not in [0, 1]")]
+    fn slippage_constructor_rejects_an_invalid_probability() {
+        WithSlippage::new(ConstantRate::new(1.0), 2.0, 0.1);
+    }
+
+    #[test]
+    fn price_improvement_needs_a_probability() {
+        let rate = || ConstantRate::new(1.0);
+
+        assert!(WithPriceImprovement::try_new(rate(), 0.0, 0.1).is_ok());
+        assert!(WithPriceImprovement::try_new(rate(), 1.0, 0.1).is_ok());
+        assert_eq!(WithPriceImprovement::try_new(rate(), 1.5, 0.1).unwrap_err(),
+                   InvalidProbability(1.5));
+        assert!(WithPriceImprovement::try_new(rate(), -0.1, 0.1).is_err());
+        assert!(WithPriceImprovement::try_new(rate(), f64::NAN, 0.1).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "not in [0, 1]")]
+    fn price_improvement_constructor_rejects_an_invalid_probability() {
+        WithPriceImprovement::new(ConstantRate::new(1.0), 2.0, 0.1);
+    }
+
+    #[test]
+    fn piecewise_linear_sorts_knots_and_rejects_non_finite_ones() {
+        let schedule = PiecewiseLinear::new(vec![(1.0, 3.0), (0.0, 1.0)]).unwrap();
+
+        assert_eq!(schedule.knots, vec![(0.0, 1.0), (1.0, 3.0)]);
+        assert_eq!(schedule.factor(0.5), 2.0);
+
+        assert_eq!(PiecewiseLinear::new(vec![(0.0, 1.0), (f64::INFINITY, 2.0)]).unwrap_err(),
+                   NonFiniteBreakpoint(f64::INFINITY, 2.0));
+        assert!(PiecewiseLinear::new(vec![(f64::NAN, 1.0), (0.0, 1.0)]).is_err());
+        assert!(PiecewiseLinear::new(vec![(0.5, f64::NAN)]).is_err());
+    }
+
+    #[test]
+    fn scheduled_drift_advances_only_on_step() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut process = ScheduledDriftBrownianMotion::new(0.25, vec![(0.5, 4.0)], 0.0).unwrap();
+
+        // Sampling, e.g. for a lookahead, leaves the schedule where it was:
+        assert_eq!(process.sample_increment(&mut rng, 0.0), 0.0);
+        assert_eq!(process.sample_increment(&mut rng, 0.0), 0.0);
+
+        process.on_step(&mut rng);
+        process.on_step(&mut rng);
+
+        assert_eq!(process.current_drift(), 4.0);
+        assert_eq!(process.sample_increment(&mut rng, 0.0), 1.0);
+
+        process.reset();
+        assert_eq!(process.current_drift(), 0.0);
+
+        assert!(ScheduledDriftBrownianMotion::new(0.25, vec![(f64::NAN, 1.0)], 0.0).is_err());
+    }
+
+    fn table() -> TabulatedRate {
+        TabulatedRate::from_pairs(vec![(0.5, 0.9), (1.0, 0.5), (2.0, 0.1), (3.0, 0.0)])
+    }
+
+    #[test]
+    fn tabulated_rate_interpolates_between_breakpoints() {
+        let rate = table();
+
+        assert_eq!(rate.match_prob(0.5), 0.9);
+        assert_eq!(rate.match_prob(1.0), 0.5);
+        assert!((rate.match_prob(0.75) - 0.7).abs() < 1e-12);
+        assert!((rate.match_prob(1.5) - 0.3).abs() < 1e-12);
+        assert!((rate.match_prob(2.75) - 0.025).abs() < 1e-12);
+    }
+
+    #[test]
+    fn tabulated_rate_is_flat_outside_the_table() {
+        let rate = table();
+
+        assert_eq!(rate.match_prob(0.0), 0.9);
+        assert_eq!(rate.match_prob(-1.0), 0.9);
+        assert_eq!(rate.match_prob(3.0), 0.0);
+        assert_eq!(rate.match_prob(100.0), 0.0);
+
+        let single = TabulatedRate::from_pairs(vec![(1.0, 0.4)]);
+
+        assert_eq!(single.match_prob(0.0), 0.4);
+        assert_eq!(single.match_prob(5.0), 0.4);
+    }
+
+    #[test]
+    fn tabulated_rate_max_offset_is_the_start_of_the_zero_tail() {
+        assert_eq!(table().max_offset(), 3.0);
+
+        let tail = TabulatedRate::from_pairs(vec![(0.0, 0.5), (1.0, 0.0), (2.0, 0.0)]);
+        let positive = TabulatedRate::from_pairs(vec![(0.0, 0.5), (1.0, 0.0), (2.0, 0.1)]);
+
+        assert_eq!(tail.max_offset(), 1.0);
+        assert_eq!(positive.max_offset(), f64::INFINITY);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one breakpoint")]
+    fn tabulated_rate_rejects_an_empty_table() {
+        TabulatedRate::from_pairs(vec![]);
+    }
+
+    #[test]
+    #[should_panic(expected = "strictly increasing")]
+    fn tabulated_rate_rejects_repeated_offsets() {
+        TabulatedRate::from_pairs(vec![(0.0, 0.5), (1.0, 0.3), (1.0, 0.2)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "must lie in [0, 1]")]
+    fn tabulated_rate_rejects_invalid_probabilities() {
+        TabulatedRate::from_pairs(vec![(0.0, 1.5), (1.0, 0.3)]);
+    }
+
+    // One-step increments from a fixed price should have variance volatility^2 * dt, so a
+    // fourfold change of dt must scale it fourfold:
+    fn assert_variance_scales_with_dt<P, F>(make: F, x: f64)
+    where
+        P: PriceDynamics,
+        F: Fn(f64) -> P,
+    {
+        for &dt in [0.005, 0.02].iter() {
+            let dynamics = make(dt);
+            let mut rng = StdRng::seed_from_u64(0);
+            let increments: Vec<_> = (0..N_SAMPLES)
+                .map(|_| dynamics.sample_increment(&mut rng, x))
+                .collect();
+
+            let [mean, var, _] = moments(&increments);
+            let expected = dynamics.volatility().powi(2) * dt;
+
+            assert!((mean - dynamics.expected_increment(x)).abs() < 0.02 * expected.sqrt());
+            assert!((var / expected - 1.0).abs() < 0.03, "variance {} at dt {}", var, dt);
+        }
+    }
+
+    #[test]
+    fn one_step_variance_is_linear_in_dt() {
+        assert_variance_scales_with_dt(|dt| BrownianMotion::new(dt, 2.0), 100.0);
+        assert_variance_scales_with_dt(|dt| BrownianMotionWithDrift::new(dt, 5.0, 2.0), 100.0);
+        assert_variance_scales_with_dt(|dt| OrnsteinUhlenbeck::new(dt, 1.0, 1.5), 3.0);
+        assert_variance_scales_with_dt(
+            |dt| OrnsteinUhlenbeckWithDrift::new(dt, 2.0, 1.0, 0.5), -1.0,
+        );
+        assert_variance_scales_with_dt(
+            |dt| Sum::new(BrownianMotion::new(dt, 2.0), OrnsteinUhlenbeck::new(dt, 1.0, 1.0)), 2.0,
+        );
+        assert_variance_scales_with_dt(
+            |dt| BrownianMotion::new(dt, 2.0).with_noise(NoiseKind::student_t(5.0).unwrap()), 100.0,
+        );
+    }
 
-        self.try_execute(offset)
+    #[test]
+    #[should_panic]
+    fn with_noise_rejects_invalid_dof() {
+        BrownianMotion::new(0.005, 2.0).with_noise(NoiseKind::StudentT { dof: 1.5 });
     }
 }
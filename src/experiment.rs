@@ -0,0 +1,206 @@
+use crate::{
+    dynamics::{InvalidDof, InvalidRate},
+    records::{JsonError, from_json, to_json},
+};
+use rsrl::fa::Parameterised;
+use serde::{Serialize, de::DeserializeOwned};
+use std::{
+    error, fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+#[derive(Debug)]
+pub enum ExperimentError {
+    Io(io::Error),
+    Csv(csv::Error),
+    Json(JsonError),
+    InvalidSaveDir(PathBuf),
+    WeightsShape { expected: [usize; 2], found: [usize; 2] },
+    InvalidCheckpoint(String),
+    InvalidConfig(String),
+}
+
+impl fmt::Display for ExperimentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExperimentError::Io(e) => write!(f, "I/O error: {}", e),
+            ExperimentError::Csv(e) => write!(f, "CSV error: {}", e),
+            ExperimentError::Json(e) => write!(f, "JSON error: {}", e),
+            ExperimentError::InvalidSaveDir(p) =>
+                write!(f, "save directory {} does not exist or is not a directory", p.display()),
+            ExperimentError::WeightsShape { expected, found } =>
+                write!(f, "expected weights of shape {:?}, found {:?}", expected, found),
+            ExperimentError::InvalidCheckpoint(msg) => write!(f, "invalid checkpoint: {}", msg),
+            ExperimentError::InvalidConfig(msg) => write!(f, "invalid configuration: {}", msg),
+        }
+    }
+}
+
+impl error::Error for ExperimentError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ExperimentError::Io(e) => Some(e),
+            ExperimentError::Csv(e) => Some(e),
+            ExperimentError::Json(e) => Some(e),
+            ExperimentError::InvalidSaveDir(_) => None,
+            ExperimentError::WeightsShape { .. } => None,
+            ExperimentError::InvalidCheckpoint(_) => None,
+            ExperimentError::InvalidConfig(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for ExperimentError {
+    fn from(e: io::Error) -> ExperimentError { ExperimentError::Io(e) }
+}
+
+impl From<csv::Error> for ExperimentError {
+    fn from(e: csv::Error) -> ExperimentError { ExperimentError::Csv(e) }
+}
+
+impl From<JsonError> for ExperimentError {
+    fn from(e: JsonError) -> ExperimentError { ExperimentError::Json(e) }
+}
+
+impl From<InvalidDof> for ExperimentError {
+    fn from(e: InvalidDof) -> ExperimentError {
+        ExperimentError::InvalidConfig(format!("price dynamics: {}", e))
+    }
+}
+
+impl From<InvalidRate> for ExperimentError {
+    fn from(e: InvalidRate) -> ExperimentError {
+        ExperimentError::InvalidConfig(format!("execution dynamics: {}", e))
+    }
+}
+
+pub fn load_config<T: DeserializeOwned, P: AsRef<Path>>(path: P) -> Result<T, ExperimentError> {
+    let contents = fs::read_to_string(path)?;
+
+    Ok(from_json(&contents)?)
+}
+
+pub fn write_json<T: Serialize, P: AsRef<Path>>(path: P, value: &T) -> Result<(), ExperimentError> {
+    fs::write(path, to_json(value)?)?;
+
+    Ok(())
+}
+
+/// Row-major copy of an approximator's weight matrix.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WeightsSnapshot {
+    pub dim: [usize; 2],
+    pub weights: Vec<f64>,
+}
+
+impl WeightsSnapshot {
+    pub fn of<T: Parameterised + ?Sized>(approximator: &T) -> WeightsSnapshot {
+        let weights = approximator.weights();
+        let (rows, cols) = weights.dim();
+
+        WeightsSnapshot {
+            dim: [rows, cols],
+            weights: weights.iter().cloned().collect(),
+        }
+    }
+
+    /// Copy rows `row_offset..` of column `col` into a single-output approximator.
+    ///
+    /// Composite policies such as `IPP` and `Gaussian` only expose stacked copies of their
+    /// weights, so a snapshot of one is restored leaf by leaf.
+    pub fn restore_column<T: Parameterised + ?Sized>(
+        &self,
+        target: &mut T,
+        col: usize,
+        row_offset: usize,
+    ) -> Result<(), ExperimentError> {
+        let [rows, cols] = self.dim;
+        let [n, outputs] = target.weights_dim();
+
+        if outputs != 1 || col >= cols || row_offset + n > rows {
+            return Err(ExperimentError::WeightsShape {
+                expected: [row_offset + n, col + 1],
+                found: self.dim,
+            });
+        }
+
+        let mut view = target.weights_view_mut();
+
+        for i in 0..n {
+            view[(i, 0)] = self.weights[(row_offset + i) * cols + col];
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct PolicyGridPoint {
+    pub time: f64,
+    pub inv: f64,
+    pub ask_offset: f64,
+    pub bid_offset: f64,
+}
+
+/// `n` evenly spaced points spanning `[start, end]` inclusive.
+pub fn linspace(start: f64, end: f64, n: usize) -> Vec<f64> {
+    match n {
+        0 => vec![],
+        1 => vec![start],
+        _ => (0..n).map(|i| start + (end - start) * i as f64 / (n - 1) as f64).collect(),
+    }
+}
+
+/// Evaluates `offsets(time, inv)` over the Cartesian product of `times` and `invs`.
+pub fn policy_grid<F>(times: &[f64], invs: &[f64], mut offsets: F) -> Vec<PolicyGridPoint>
+where
+    F: FnMut(f64, f64) -> [f64; 2],
+{
+    times.iter().flat_map(|&time| invs.iter().map(move |&inv| (time, inv)))
+        .map(|(time, inv)| {
+            let [ask_offset, bid_offset] = offsets(time, inv);
+
+            PolicyGridPoint { time, inv, ask_offset, bid_offset, }
+        })
+        .collect()
+}
+
+pub fn write_csv<T: Serialize, P: AsRef<Path>>(path: P, records: &[T]) -> Result<(), ExperimentError> {
+    let mut writer = csv::Writer::from_path(path)?;
+
+    for r in records { writer.serialize(r)?; }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+// Only async-signal-safe calls here; a second Ctrl-C falls through to the default and kills.
+extern "C" fn on_interrupt(_: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+
+    unsafe { libc::signal(libc::SIGINT, libc::SIG_DFL); }
+}
+
+/// Turn Ctrl-C (SIGINT) into a flag, polled with `interrupted`, so that a training loop can
+/// finish its episode and save its state before exiting.
+pub fn catch_interrupt() {
+    let handler = on_interrupt as extern "C" fn(libc::c_int);
+
+    unsafe { libc::signal(libc::SIGINT, handler as libc::sighandler_t); }
+}
+
+pub fn interrupted() -> bool { INTERRUPTED.load(Ordering::SeqCst) }
+
+pub fn validate_save_dir<P: AsRef<Path>>(save_dir: P) -> Result<(), ExperimentError> {
+    let save_dir = save_dir.as_ref();
+
+    if save_dir.is_dir() {
+        Ok(())
+    } else {
+        Err(ExperimentError::InvalidSaveDir(save_dir.to_path_buf()))
+    }
+}
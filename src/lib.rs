@@ -1,18 +1,60 @@
+// With `--no-default-features` only `utils` is built; the `slog` impls need `logging`.
+#[cfg(feature = "sim")]
 extern crate rand;
+#[cfg(feature = "sim")]
 extern crate rand_distr;
 
+#[cfg(feature = "sim")]
+extern crate rayon;
+#[cfg(feature = "sim")]
+extern crate libc;
+
+#[cfg(feature = "sim")]
 extern crate rsrl;
+#[cfg(feature = "logging")]
 extern crate slog;
 
+#[cfg(feature = "sim")]
+extern crate csv;
+#[cfg(feature = "sim")]
+extern crate serde;
+#[cfg(feature = "sim")]
+#[macro_use]
+extern crate serde_derive;
+
 pub mod utils;
+#[cfg(feature = "sim")]
 pub mod dynamics;
+#[cfg(feature = "sim")]
 pub mod strategies;
+#[cfg(feature = "sim")]
+pub mod records;
+#[cfg(feature = "sim")]
+pub mod experiment;
+#[cfg(feature = "sim")]
+pub mod checkpoint;
 
+#[cfg(feature = "sim")]
 mod trader;
+#[cfg(feature = "sim")]
 pub use self::trader::*;
 
+#[cfg(feature = "sim")]
 mod adversary;
+#[cfg(feature = "sim")]
 pub use self::adversary::*;
 
+#[cfg(feature = "sim")]
 mod zero_sum;
+#[cfg(feature = "sim")]
 pub use self::zero_sum::*;
+
+#[cfg(feature = "sim")]
+mod simulation;
+#[cfg(feature = "sim")]
+pub use self::simulation::*;
+
+#[cfg(feature = "sim")]
+mod recording;
+#[cfg(feature = "sim")]
+pub use self::recording::*;
@@ -0,0 +1,51 @@
+use rsrl::domains::{Action, Domain, Observation, State, Transition};
+
+/// Decorator that forwards to an inner domain and keeps every transition it steps through, for
+/// building offline datasets from a behaviour policy.
+///
+/// Each `Transition` holds the `(state, action, reward, next_state)` tuple, with `terminated()`
+/// as the done flag. The log is never cleared, so consecutive episodes, separated by a reset of
+/// the inner domain through `inner_mut`, are stored back to back.
+#[derive(Clone)]
+pub struct RecordingDomain<D: Domain> {
+    inner: D,
+    transitions: Vec<Transition<State<D>, Action<D>>>,
+}
+
+impl<D: Domain> RecordingDomain<D> {
+    pub fn new(inner: D) -> Self {
+        RecordingDomain { inner, transitions: vec![], }
+    }
+
+    pub fn inner(&self) -> &D { &self.inner }
+
+    pub fn inner_mut(&mut self) -> &mut D { &mut self.inner }
+
+    pub fn transitions(&self) -> &[Transition<State<D>, Action<D>>] { &self.transitions }
+
+    pub fn into_transitions(self) -> Vec<Transition<State<D>, Action<D>>> { self.transitions }
+}
+
+impl<D> Domain for RecordingDomain<D>
+where
+    D: Domain,
+    State<D>: Clone,
+    Action<D>: Clone,
+{
+    type StateSpace = D::StateSpace;
+    type ActionSpace = D::ActionSpace;
+
+    fn emit(&self) -> Observation<State<D>> { self.inner.emit() }
+
+    fn step(&mut self, action: Action<D>) -> Transition<State<D>, Action<D>> {
+        let transition = self.inner.step(action);
+
+        self.transitions.push(transition.clone());
+
+        transition
+    }
+
+    fn state_space(&self) -> Self::StateSpace { self.inner.state_space() }
+
+    fn action_space(&self) -> Self::ActionSpace { self.inner.action_space() }
+}
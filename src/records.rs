@@ -0,0 +1,794 @@
+use serde::{
+    de::{
+        self, DeserializeOwned, Deserializer, IntoDeserializer, Visitor,
+        value::{MapAccessDeserializer, MapDeserializer, SeqDeserializer},
+    },
+    ser::{self, Serialize, Serializer},
+};
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    str::FromStr,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum RecordFormat {
+    #[serde(rename = "csv")]
+    Csv,
+    #[serde(rename = "jsonl")]
+    JsonLines,
+}
+
+impl RecordFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            RecordFormat::Csv => "csv",
+            RecordFormat::JsonLines => "jsonl",
+        }
+    }
+}
+
+impl FromStr for RecordFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<RecordFormat, String> {
+        match s {
+            "csv" => Ok(RecordFormat::Csv),
+            "jsonl" => Ok(RecordFormat::JsonLines),
+            _ => Err(format!("Unknown record format '{}', expected csv or jsonl.", s)),
+        }
+    }
+}
+
+pub enum RecordWriter {
+    Csv(Box<csv::Writer<File>>),
+    JsonLines(BufWriter<File>),
+}
+
+impl RecordWriter {
+    pub fn from_path<P: AsRef<Path>>(format: RecordFormat, path: P) -> io::Result<RecordWriter> {
+        Ok(match format {
+            RecordFormat::Csv => RecordWriter::Csv(Box::new(csv::Writer::from_path(path)?)),
+            RecordFormat::JsonLines => RecordWriter::JsonLines(BufWriter::new(File::create(path)?)),
+        })
+    }
+
+    pub fn serialize<T: Serialize>(&mut self, record: T) -> io::Result<()> {
+        match self {
+            RecordWriter::Csv(w) => Ok(w.serialize(record)?),
+            RecordWriter::JsonLines(w) => {
+                let line = to_json(&record)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.0))?;
+
+                writeln!(w, "{}", line)
+            },
+        }
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        match self {
+            RecordWriter::Csv(w) => w.flush(),
+            RecordWriter::JsonLines(w) => w.flush(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct JsonError(String);
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { f.write_str(&self.0) }
+}
+
+impl std::error::Error for JsonError {}
+
+impl ser::Error for JsonError {
+    fn custom<T: fmt::Display>(msg: T) -> JsonError { JsonError(msg.to_string()) }
+}
+
+impl de::Error for JsonError {
+    fn custom<T: fmt::Display>(msg: T) -> JsonError { JsonError(msg.to_string()) }
+}
+
+pub fn to_json<T: Serialize + ?Sized>(value: &T) -> Result<String, JsonError> {
+    let mut out = String::new();
+
+    value.serialize(Json(&mut out))?;
+
+    Ok(out)
+}
+
+fn write_str(out: &mut String, s: &str) {
+    out.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+}
+
+// Minimal serde serializer emitting compact JSON; non-finite floats are written as null.
+struct Json<'a>(&'a mut String);
+
+struct Compound<'a> {
+    out: &'a mut String,
+    first: bool,
+    close: &'static str,
+}
+
+impl<'a> Compound<'a> {
+    fn open(out: &'a mut String, open: &str, close: &'static str) -> Compound<'a> {
+        out.push_str(open);
+
+        Compound { out, first: true, close, }
+    }
+
+    fn separate(&mut self) {
+        if !self.first { self.out.push(','); }
+
+        self.first = false;
+    }
+
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), JsonError> {
+        self.separate();
+
+        value.serialize(Json(self.out))
+    }
+
+    fn field<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<(), JsonError> {
+        self.separate();
+
+        write_str(self.out, key);
+        self.out.push(':');
+
+        value.serialize(Json(self.out))
+    }
+
+    fn close(self) -> Result<(), JsonError> {
+        self.out.push_str(self.close);
+
+        Ok(())
+    }
+}
+
+macro_rules! impl_display_number {
+    ($($name:ident: $type:ty),*) => {
+        $(fn $name(self, v: $type) -> Result<(), JsonError> {
+            self.0.push_str(&v.to_string());
+
+            Ok(())
+        })*
+    }
+}
+
+impl<'a> Serializer for Json<'a> {
+    type Ok = ();
+    type Error = JsonError;
+
+    type SerializeSeq = Compound<'a>;
+    type SerializeTuple = Compound<'a>;
+    type SerializeTupleStruct = Compound<'a>;
+    type SerializeTupleVariant = Compound<'a>;
+    type SerializeMap = Compound<'a>;
+    type SerializeStruct = Compound<'a>;
+    type SerializeStructVariant = Compound<'a>;
+
+    impl_display_number!(
+        serialize_i8: i8, serialize_i16: i16, serialize_i32: i32, serialize_i64: i64,
+        serialize_u8: u8, serialize_u16: u16, serialize_u32: u32, serialize_u64: u64
+    );
+
+    fn serialize_bool(self, v: bool) -> Result<(), JsonError> {
+        self.0.push_str(if v { "true" } else { "false" });
+
+        Ok(())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), JsonError> { self.serialize_f64(v as f64) }
+
+    fn serialize_f64(self, v: f64) -> Result<(), JsonError> {
+        if v.is_finite() {
+            self.0.push_str(&format!("{:?}", v));
+        } else {
+            self.0.push_str("null");
+        }
+
+        Ok(())
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), JsonError> {
+        self.serialize_str(&v.to_string())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), JsonError> {
+        write_str(self.0, v);
+
+        Ok(())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), JsonError> {
+        let mut seq = Compound::open(self.0, "[", "]");
+
+        for b in v { seq.element(b)?; }
+
+        seq.close()
+    }
+
+    fn serialize_none(self) -> Result<(), JsonError> { self.serialize_unit() }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), JsonError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), JsonError> {
+        self.0.push_str("null");
+
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<(), JsonError> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(self, _: &'static str, _: u32,
+                              variant: &'static str) -> Result<(), JsonError>
+    {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _: &'static str,
+                                                       value: &T) -> Result<(), JsonError>
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _: &'static str, _: u32,
+                                                        variant: &'static str,
+                                                        value: &T) -> Result<(), JsonError>
+    {
+        let mut map = Compound::open(self.0, "{", "}");
+
+        map.field(variant, value)?;
+        map.close()
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Compound<'a>, JsonError> {
+        Ok(Compound::open(self.0, "[", "]"))
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Compound<'a>, JsonError> {
+        Ok(Compound::open(self.0, "[", "]"))
+    }
+
+    fn serialize_tuple_struct(self, _: &'static str, _: usize) -> Result<Compound<'a>, JsonError> {
+        Ok(Compound::open(self.0, "[", "]"))
+    }
+
+    fn serialize_tuple_variant(self, _: &'static str, _: u32, variant: &'static str,
+                               _: usize) -> Result<Compound<'a>, JsonError>
+    {
+        self.0.push('{');
+        write_str(self.0, variant);
+
+        Ok(Compound::open(self.0, ":[", "]}"))
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Compound<'a>, JsonError> {
+        Ok(Compound::open(self.0, "{", "}"))
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Compound<'a>, JsonError> {
+        Ok(Compound::open(self.0, "{", "}"))
+    }
+
+    fn serialize_struct_variant(self, _: &'static str, _: u32, variant: &'static str,
+                                _: usize) -> Result<Compound<'a>, JsonError>
+    {
+        self.0.push('{');
+        write_str(self.0, variant);
+
+        Ok(Compound::open(self.0, ":{", "}}"))
+    }
+}
+
+impl<'a> ser::SerializeSeq for Compound<'a> {
+    type Ok = ();
+    type Error = JsonError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), JsonError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), JsonError> { self.close() }
+}
+
+impl<'a> ser::SerializeTuple for Compound<'a> {
+    type Ok = ();
+    type Error = JsonError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), JsonError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), JsonError> { self.close() }
+}
+
+impl<'a> ser::SerializeTupleStruct for Compound<'a> {
+    type Ok = ();
+    type Error = JsonError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), JsonError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), JsonError> { self.close() }
+}
+
+impl<'a> ser::SerializeTupleVariant for Compound<'a> {
+    type Ok = ();
+    type Error = JsonError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), JsonError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), JsonError> { self.close() }
+}
+
+impl<'a> ser::SerializeMap for Compound<'a> {
+    type Ok = ();
+    type Error = JsonError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), JsonError> {
+        let key = to_json(key)?;
+
+        self.separate();
+
+        // JSON object keys must be strings:
+        if key.starts_with('"') {
+            self.out.push_str(&key);
+        } else {
+            write_str(self.out, &key);
+        }
+
+        self.out.push(':');
+
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), JsonError> {
+        value.serialize(Json(self.out))
+    }
+
+    fn end(self) -> Result<(), JsonError> { self.close() }
+}
+
+impl<'a> ser::SerializeStruct for Compound<'a> {
+    type Ok = ();
+    type Error = JsonError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str,
+                                              value: &T) -> Result<(), JsonError>
+    {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<(), JsonError> { self.close() }
+}
+
+impl<'a> ser::SerializeStructVariant for Compound<'a> {
+    type Ok = ();
+    type Error = JsonError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str,
+                                              value: &T) -> Result<(), JsonError>
+    {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<(), JsonError> { self.close() }
+}
+
+pub fn from_json<T: DeserializeOwned>(s: &str) -> Result<T, JsonError> {
+    let mut parser = Parser { input: s.as_bytes(), pos: 0, };
+
+    let value = parser.parse_value()?;
+
+    parser.skip_whitespace();
+
+    if parser.pos < parser.input.len() {
+        return Err(parser.error("trailing characters"));
+    }
+
+    T::deserialize(value)
+}
+
+// Parsed JSON document; deserialization is delegated to serde's value deserializers.
+enum JsonValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    Str(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+struct Parser<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, msg: &str) -> JsonError {
+        JsonError(format!("{} at byte {}", msg, self.pos))
+    }
+
+    fn peek(&self) -> Option<u8> { self.input.get(self.pos).cloned() }
+
+    fn next(&mut self) -> Result<u8, JsonError> {
+        let b = self.peek().ok_or_else(|| self.error("unexpected end of input"))?;
+
+        self.pos += 1;
+
+        Ok(b)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), JsonError> {
+        if self.input[self.pos..].starts_with(token.as_bytes()) {
+            self.pos += token.len();
+
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", token)))
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<JsonValue, JsonError> {
+        self.skip_whitespace();
+
+        match self.peek() {
+            Some(b'n') => self.expect("null").map(|_| JsonValue::Null),
+            Some(b't') => self.expect("true").map(|_| JsonValue::Bool(true)),
+            Some(b'f') => self.expect("false").map(|_| JsonValue::Bool(false)),
+            Some(b'"') => self.parse_string().map(JsonValue::Str),
+            Some(b'[') => self.parse_array(),
+            Some(b'{') => self.parse_object(),
+            Some(b'-') | Some(b'0'..=b'9') => self.parse_number(),
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<JsonValue, JsonError> {
+        let start = self.pos;
+
+        while let Some(b'0'..=b'9') | Some(b'-') | Some(b'+') | Some(b'.')
+            | Some(b'e') | Some(b'E') = self.peek()
+        {
+            self.pos += 1;
+        }
+
+        // The scanned bytes are ASCII, so this slice is valid UTF-8:
+        let lexeme = ::std::str::from_utf8(&self.input[start..self.pos]).unwrap();
+        let invalid = || JsonError(format!("invalid number '{}' at byte {}", lexeme, start));
+
+        if lexeme.contains(&['.', 'e', 'E'][..]) {
+            lexeme.parse().map(JsonValue::Float).map_err(|_| invalid())
+        } else if lexeme.starts_with('-') {
+            lexeme.parse().map(JsonValue::Int).map_err(|_| invalid())
+        } else {
+            lexeme.parse().map(JsonValue::UInt).map_err(|_| invalid())
+        }
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, JsonError> {
+        let mut code = 0;
+
+        for _ in 0..4 {
+            let digit = (self.next()? as char).to_digit(16)
+                .ok_or_else(|| self.error("invalid unicode escape"))?;
+
+            code = code * 16 + digit;
+        }
+
+        Ok(code)
+    }
+
+    fn parse_string(&mut self) -> Result<String, JsonError> {
+        let mut bytes = vec![];
+
+        self.expect("\"")?;
+
+        loop {
+            match self.next()? {
+                b'"' => break,
+                b'\\' => {
+                    let c = match self.next()? {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => {
+                            let mut code = self.parse_hex4()?;
+
+                            // Combine UTF-16 surrogate pairs:
+                            if (0xD800..0xDC00).contains(&code) {
+                                self.expect("\\u")?;
+
+                                let low = self.parse_hex4()?;
+
+                                if !(0xDC00..0xE000).contains(&low) {
+                                    return Err(self.error("invalid surrogate pair"));
+                                }
+
+                                code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                            }
+
+                            ::std::char::from_u32(code)
+                                .ok_or_else(|| self.error("invalid unicode escape"))?
+                        },
+                        _ => return Err(self.error("invalid escape")),
+                    };
+
+                    bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                },
+                b => bytes.push(b),
+            }
+        }
+
+        String::from_utf8(bytes).map_err(|_| self.error("invalid UTF-8 in string"))
+    }
+
+    fn parse_array(&mut self) -> Result<JsonValue, JsonError> {
+        let mut elements = vec![];
+
+        self.expect("[")?;
+        self.skip_whitespace();
+
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+
+            return Ok(JsonValue::Array(elements));
+        }
+
+        loop {
+            elements.push(self.parse_value()?);
+            self.skip_whitespace();
+
+            match self.next()? {
+                b',' => continue,
+                b']' => return Ok(JsonValue::Array(elements)),
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<JsonValue, JsonError> {
+        let mut entries = vec![];
+
+        self.expect("{")?;
+        self.skip_whitespace();
+
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+
+            return Ok(JsonValue::Object(entries));
+        }
+
+        loop {
+            self.skip_whitespace();
+
+            let key = self.parse_string()?;
+
+            self.skip_whitespace();
+            self.expect(":")?;
+
+            entries.push((key, self.parse_value()?));
+            self.skip_whitespace();
+
+            match self.next()? {
+                b',' => continue,
+                b'}' => return Ok(JsonValue::Object(entries)),
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+}
+
+impl<'de> IntoDeserializer<'de, JsonError> for JsonValue {
+    type Deserializer = JsonValue;
+
+    fn into_deserializer(self) -> JsonValue { self }
+}
+
+impl<'de> Deserializer<'de> for JsonValue {
+    type Error = JsonError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, JsonError> {
+        match self {
+            JsonValue::Null => visitor.visit_unit(),
+            JsonValue::Bool(v) => visitor.visit_bool(v),
+            JsonValue::Int(v) => visitor.visit_i64(v),
+            JsonValue::UInt(v) => visitor.visit_u64(v),
+            JsonValue::Float(v) => visitor.visit_f64(v),
+            JsonValue::Str(v) => visitor.visit_string(v),
+            JsonValue::Array(v) => {
+                let mut seq = SeqDeserializer::new(v.into_iter());
+                let value = visitor.visit_seq(&mut seq)?;
+
+                seq.end()?;
+
+                Ok(value)
+            },
+            JsonValue::Object(v) => {
+                let mut map = MapDeserializer::new(v.into_iter());
+                let value = visitor.visit_map(&mut map)?;
+
+                map.end()?;
+
+                Ok(value)
+            },
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, JsonError> {
+        match self {
+            JsonValue::Null => visitor.visit_none(),
+            value => visitor.visit_some(value),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _: &'static str,
+                                                   visitor: V) -> Result<V::Value, JsonError>
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    // Enums are externally tagged, matching the serializer above:
+    fn deserialize_enum<V: Visitor<'de>>(self, _: &'static str, _: &'static [&'static str],
+                                         visitor: V) -> Result<V::Value, JsonError>
+    {
+        match self {
+            JsonValue::Str(variant) => visitor.visit_enum(variant.into_deserializer()),
+            JsonValue::Object(ref entries) if entries.len() == 1 => match self {
+                JsonValue::Object(entries) => visitor.visit_enum(
+                    MapAccessDeserializer::new(MapDeserializer::new(entries.into_iter()))
+                ),
+                _ => unreachable!(),
+            },
+            _ => Err(de::Error::custom("expected a string or single-key object for enum")),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Event {
+        Reset,
+        Fill(f64),
+        Quote { ask: f64, bid: f64 },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Inner {
+        name: String,
+        values: Vec<f64>,
+        limit: Option<u32>,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Outer {
+        id: i64,
+        inner: Inner,
+        events: Vec<Event>,
+        flag: bool,
+    }
+
+    fn round_trip<T>(value: &T) -> T
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        from_json(&to_json(value).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn nested_structs_round_trip() {
+        let value = Outer {
+            id: -7,
+            inner: Inner { name: "lob".to_owned(), values: vec![1.0, -2.5], limit: None, },
+            events: vec![Event::Reset, Event::Fill(0.25), Event::Quote { ask: 1.5, bid: 0.5 }],
+            flag: true,
+        };
+
+        assert_eq!(round_trip(&value), value);
+        assert_eq!(round_trip(&Inner { limit: Some(3), ..value.inner }).limit, Some(3));
+    }
+
+    #[test]
+    fn floats_round_trip_exactly() {
+        let floats = vec![
+            0.1, -0.0, 1.5e10, 1e300, -2.5e-8, 1e-300,
+            5e-324, f64::MAX, f64::MIN_POSITIVE,
+        ];
+        let parsed: Vec<f64> = round_trip(&floats);
+
+        for (x, y) in floats.iter().zip(parsed.iter()) {
+            assert_eq!(x.to_bits(), y.to_bits(), "{} did not round-trip", x);
+        }
+
+        assert_eq!(from_json::<f64>("2E+3").unwrap(), 2000.0);
+        assert_eq!(from_json::<f64>("-4").unwrap(), -4.0);
+        assert_eq!(to_json(&f64::NAN).unwrap(), "null");
+    }
+
+    #[test]
+    fn strings_round_trip_with_escapes_and_unicode() {
+        let s = "quote \" backslash \\ slash / \n\r\t \u{1} \u{1f} é ü 日本 🦀".to_owned();
+        let json = to_json(&s).unwrap();
+
+        assert!(!json.contains('\n') && json.contains("\\u0001"));
+        assert_eq!(round_trip(&s), s);
+
+        assert_eq!(from_json::<String>(r#""é\/\b\f""#).unwrap(), "é/\u{8}\u{c}");
+        assert_eq!(from_json::<String>(r#""🦀""#).unwrap(), "🦀");
+    }
+
+    #[test]
+    fn malformed_input_is_rejected() {
+        let invalid = [
+            "", "[1, 2", "{\"a\" 1}", "[1] x", "\"unterminated", "nul", "1.2.3", "-",
+            r#""\q""#, r#""\u12g4""#, r#""\udc00""#, r#""\ud83e""#, r#""\ud83eA""#,
+            r#""\ud83e\u0041""#, r#""\ud83e\ue000""#,
+        ];
+
+        for s in invalid.iter() {
+            assert!(from_json::<Vec<f64>>(s).is_err() && from_json::<String>(s).is_err(),
+                    "accepted {:?}", s);
+        }
+    }
+
+    #[test]
+    fn mismatched_documents_are_rejected() {
+        let missing = r#"{"name": "lob", "limit": 2}"#;
+        let wrong_type = r#"{"name": "lob", "values": ["1.0"], "limit": null}"#;
+        let negative = r#"{"name": "lob", "values": [], "limit": -1}"#;
+
+        assert!(from_json::<Inner>(r#"{"name": "lob", "values": [], "limit": 2}"#).is_ok());
+        assert!(from_json::<Inner>(missing).unwrap_err().to_string().contains("values"));
+        assert!(from_json::<Inner>(wrong_type).is_err());
+        assert!(from_json::<Inner>(negative).is_err());
+        assert!(from_json::<Event>(r#"{"Fill": 1.0, "Reset": null}"#).is_err());
+        assert!(from_json::<Event>(r#""Unknown""#).is_err());
+    }
+}
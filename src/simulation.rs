@@ -0,0 +1,238 @@
+use crate::{
+    TraderDomain,
+    dynamics::{PriceDynamics, ExecutionDynamics},
+    strategies::QuotingStrategy,
+    utils::Estimate,
+};
+use rand::{Rng, rngs::StdRng};
+use rayon::prelude::*;
+use rsrl::domains::Domain;
+use std::fmt;
+
+/// Number of equal time buckets over `[0, 1]` in `EpisodeSummary::inventory_profile`.
+pub const INVENTORY_BUCKETS: usize = 10;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct EpisodeSummary {
+    pub terminal_wealth: f64,
+    pub terminal_inventory: f64,
+    pub cumulative_reward: f64,
+    pub avg_spread: f64,
+    pub realized_spread: f64,
+    pub steps: usize,
+    pub ask_fills: usize,
+    pub bid_fills: usize,
+    /// Mean inventory carried into the steps that start in each time bucket.
+    pub inventory_profile: Vec<f64>,
+}
+
+impl fmt::Display for EpisodeSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "terminal wealth:    {:.4}", self.terminal_wealth)?;
+        writeln!(f, "terminal inventory: {}", self.terminal_inventory)?;
+        writeln!(f, "cumulative reward:  {:.4}", self.cumulative_reward)?;
+        writeln!(f, "average spread:     {:.4}", self.avg_spread)?;
+        writeln!(f, "realized spread:    {:.4}", self.realized_spread)?;
+        write!(f, "steps: {}, fills: {} ask / {} bid", self.steps, self.ask_fills, self.bid_fills)
+    }
+}
+
+pub fn simulate_episode<P, E, R, S>(domain: &mut TraderDomain<P, E, R>, strategy: &S) -> EpisodeSummary
+where
+    P: PriceDynamics,
+    E: ExecutionDynamics,
+    R: Rng,
+    S: QuotingStrategy + ?Sized,
+{
+    simulate_episode_with(domain, |d| d.analytic_action(strategy))
+}
+
+/// As `simulate_episode`, but quoting with `act`, e.g. a learned policy reading `emit()`.
+pub fn simulate_episode_with<P, E, R, F>(domain: &mut TraderDomain<P, E, R>,
+                                         mut act: F) -> EpisodeSummary
+where
+    P: PriceDynamics,
+    E: ExecutionDynamics,
+    R: Rng,
+    F: FnMut(&TraderDomain<P, E, R>) -> [f64; 2],
+{
+    let mut steps = 0;
+    let mut reward_sum = 0.0;
+
+    let mut bucket_sums = [0.0; INVENTORY_BUCKETS];
+    let mut bucket_counts = [0usize; INVENTORY_BUCKETS];
+
+    loop {
+        // Counting steps rather than reading the time avoids rounding at bucket edges:
+        let bucket = (steps * INVENTORY_BUCKETS / domain.horizon_steps().max(1))
+            .min(INVENTORY_BUCKETS - 1);
+
+        bucket_sums[bucket] += domain.inv;
+        bucket_counts[bucket] += 1;
+
+        let a = act(domain);
+        let t = domain.step(a);
+
+        steps += 1;
+        reward_sum += t.reward;
+
+        if t.terminated() {
+            return EpisodeSummary {
+                terminal_wealth: domain.wealth,
+                terminal_inventory: domain.inv_terminal,
+                cumulative_reward: reward_sum,
+                avg_spread: domain.time_weighted_avg_spread(),
+                realized_spread: domain.realized_spread(),
+                steps,
+                ask_fills: domain.asks_filled(),
+                bid_fills: domain.bids_filled(),
+                inventory_profile: bucket_sums.iter().zip(bucket_counts.iter())
+                    .map(|(&sum, &n)| if n > 0 { sum / n as f64 } else { 0.0 })
+                    .collect(),
+            }
+        }
+    }
+}
+
+/// Mean and stddev across episodes of each scalar field of `EpisodeSummary`.
+#[derive(Clone, Copy, Debug)]
+pub struct SummaryStats {
+    pub episodes: usize,
+
+    pub terminal_wealth: Estimate,
+    pub terminal_inventory: Estimate,
+    pub cumulative_reward: Estimate,
+    pub avg_spread: Estimate,
+    pub realized_spread: Estimate,
+    pub steps: Estimate,
+    pub ask_fills: Estimate,
+    pub bid_fills: Estimate,
+}
+
+pub fn aggregate(summaries: &[EpisodeSummary]) -> SummaryStats {
+    let estimate = |metric: fn(&EpisodeSummary) -> f64| {
+        let values: Vec<_> = summaries.iter().map(metric).collect();
+
+        Estimate::from_slice(&values)
+    };
+
+    SummaryStats {
+        episodes: summaries.len(),
+
+        terminal_wealth: estimate(|s| s.terminal_wealth),
+        terminal_inventory: estimate(|s| s.terminal_inventory),
+        cumulative_reward: estimate(|s| s.cumulative_reward),
+        avg_spread: estimate(|s| s.avg_spread),
+        realized_spread: estimate(|s| s.realized_spread),
+        steps: estimate(|s| s.steps as f64),
+        ask_fills: estimate(|s| s.ask_fills as f64),
+        bid_fills: estimate(|s| s.bid_fills as f64),
+    }
+}
+
+/// Per-bucket mean and stddev of `inventory_profile` across episodes.
+pub fn inventory_profile_stats(summaries: &[EpisodeSummary]) -> Vec<Estimate> {
+    (0..INVENTORY_BUCKETS).map(|k| {
+        let values: Vec<_> = summaries.iter().map(|s| s.inventory_profile[k]).collect();
+
+        Estimate::from_slice(&values)
+    }).collect()
+}
+
+pub fn simulate_batch<P, E, R, S, F>(n: usize, builder: F, strategy: &S) -> Vec<EpisodeSummary>
+where
+    P: PriceDynamics,
+    E: ExecutionDynamics,
+    R: Rng,
+    S: QuotingStrategy + Sync + ?Sized,
+    F: Fn() -> TraderDomain<P, E, R> + Sync + Send,
+{
+    // Each worker builds a single domain and resets it between episodes:
+    (0..n)
+        .into_par_iter()
+        .map_init(&builder, |domain, _| {
+            domain.reset();
+
+            simulate_episode(domain, strategy)
+        })
+        .collect()
+}
+
+pub fn simulate_batch_seeded<P, E, S, F>(n: usize, seed: u64, builder: F,
+                                         strategy: &S) -> Vec<EpisodeSummary>
+where
+    P: PriceDynamics,
+    E: ExecutionDynamics,
+    S: QuotingStrategy + Sync + ?Sized,
+    F: Fn(u64) -> TraderDomain<P, E, StdRng> + Sync + Send,
+{
+    simulate_batch_seeded_with(n, seed, builder, |d| d.analytic_action(strategy))
+}
+
+pub fn simulate_batch_seeded_with<P, E, F, A>(n: usize, seed: u64, builder: F,
+                                              act: A) -> Vec<EpisodeSummary>
+where
+    P: PriceDynamics,
+    E: ExecutionDynamics,
+    F: Fn(u64) -> TraderDomain<P, E, StdRng> + Sync + Send,
+    A: Fn(&TraderDomain<P, E, StdRng>) -> [f64; 2] + Sync + Send,
+{
+    // Episode i always uses seed + i, so strategies can be compared on common random numbers:
+    (0..n)
+        .into_par_iter()
+        .map(|i| simulate_episode_with(&mut builder(seed + i as u64), &act))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dynamics::ASDynamics, strategies::ExponentialUtilityStrategy};
+
+    const N_EPISODES: usize = 2000;
+
+    // Small enough that the skewed quotes are almost never clamped at a zero offset:
+    const GAMMA: f64 = 0.01;
+
+    #[test]
+    fn seeded_episodes_are_reproducible() {
+        let strategy = ExponentialUtilityStrategy::new(1.5, 0.1, 2.0);
+        let run = |seed| simulate_episode(
+            &mut TraderDomain::new(ASDynamics::seeded(seed), 0.0), &strategy,
+        );
+
+        let [a, b, c] = [run(42), run(42), run(43)];
+
+        assert_eq!(a.terminal_wealth.to_bits(), b.terminal_wealth.to_bits());
+        assert_eq!(a.terminal_inventory.to_bits(), b.terminal_inventory.to_bits());
+        assert_ne!(a.terminal_wealth, c.terminal_wealth);
+    }
+
+    #[test]
+    fn as_quotes_match_analytic_spread_and_inventory() {
+        let domain = TraderDomain::new(ASDynamics::seeded(0), 0.0);
+        let k = domain.dynamics.execution_dynamics.decay;
+        let dt = domain.dynamics.dt;
+        let strategy = ExponentialUtilityStrategy::new(
+            k, GAMMA, domain.dynamics.price_dynamics.volatility,
+        );
+
+        let summaries = simulate_batch_seeded(
+            N_EPISODES, 0, |seed| TraderDomain::new(ASDynamics::seeded(seed), 0.0), &strategy,
+        );
+        let stats = aggregate(&summaries);
+
+        // Quotes are set at the start of each step, so the analytic spread is averaged over the
+        // left end of every step:
+        let n_steps = domain.horizon_steps();
+        let analytic_spread = (0..n_steps)
+            .map(|i| strategy.spread(i as f64 * dt))
+            .sum::<f64>() / n_steps as f64;
+
+        assert!((stats.avg_spread.0 - analytic_spread).abs() < 1e-3 * analytic_spread);
+
+        let inv_stderr = stats.terminal_inventory.1 / (N_EPISODES as f64).sqrt();
+
+        assert!(stats.terminal_inventory.0.abs() < 4.0 * inv_stderr);
+    }
+}
@@ -1,4 +1,19 @@
-#[derive(Debug)]
+/// Maps a state to `[ask_offset, bid_offset]`, each measured outward from the mid `price`.
+///
+/// Strategies defined by a reservation price `rp` and spread `sp` quote
+/// `[rp + sp / 2 - price, price - (rp - sp / 2)]`: the offsets sum to `sp`, are symmetric when
+/// `rp == price`, and a long inventory (`rp < price`) tightens the ask and widens the bid.
+pub trait QuotingStrategy {
+    fn compute(&self, time: f64, price: f64, inventory: f64) -> [f64; 2];
+}
+
+impl<S: QuotingStrategy + ?Sized> QuotingStrategy for Box<S> {
+    fn compute(&self, time: f64, price: f64, inventory: f64) -> [f64; 2] {
+        (**self).compute(time, price, inventory)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct LinearUtilityStrategy {
     k: f64,
 }
@@ -7,13 +22,20 @@ impl LinearUtilityStrategy {
     pub fn new(k: f64) -> LinearUtilityStrategy {
         LinearUtilityStrategy { k, }
     }
+}
 
-    pub fn compute(&self, _: f64, _: f64, _: f64) -> [f64; 2] {
+impl QuotingStrategy for LinearUtilityStrategy {
+    fn compute(&self, _: f64, _: f64, _: f64) -> [f64; 2] {
         [1.0 / self.k, 1.0 / self.k]
     }
 }
 
-#[derive(Debug)]
+/// Risk-neutral quotes under a terminal inventory penalty `-eta * inventory^2`.
+///
+/// Filling an ask changes the penalty by `eta * (2q - 1)` and a bid by `-eta * (2q + 1)`, so the
+/// optimal offsets are `1/k + eta -/+ 2 * eta * q`: the reservation price is skewed by `2 * eta * q`
+/// and the convexity of the penalty widens the spread to `2/k + 2 * eta`, independent of `q`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct LinearUtilityTerminalPenaltyStrategy {
     k: f64,
     eta: f64,
@@ -23,16 +45,18 @@ impl LinearUtilityTerminalPenaltyStrategy {
     pub fn new(k: f64, eta: f64) -> LinearUtilityTerminalPenaltyStrategy {
         LinearUtilityTerminalPenaltyStrategy { k, eta, }
     }
+}
 
-    pub fn compute(&self, _: f64, price: f64, inventory: f64) -> [f64; 2] {
+impl QuotingStrategy for LinearUtilityTerminalPenaltyStrategy {
+    fn compute(&self, _: f64, price: f64, inventory: f64) -> [f64; 2] {
         let rp = price - 2.0 * inventory * self.eta;
-        let sp = 2.0 / self.k + self.eta;
+        let sp = 2.0 / self.k + 2.0 * self.eta;
 
         [rp + sp / 2.0 - price, price - (rp - sp / 2.0)]
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ExponentialUtilityStrategy {
     k: f64,
     gamma: f64,
@@ -44,12 +68,164 @@ impl ExponentialUtilityStrategy {
         ExponentialUtilityStrategy { k, gamma, volatility, }
     }
 
-    pub fn compute(&self, time: f64, price: f64, inventory: f64) -> [f64; 2] {
+    pub fn reservation_price(&self, time: f64, price: f64, inventory: f64) -> f64 {
         let gss = self.gamma * self.volatility * self.volatility;
 
-        let rp = price - inventory * gss * (1.0 - time);
-        let sp = gss * (1.0 - time) + (2.0 / self.gamma) * (1.0 + self.gamma / self.k).ln();
+        price - inventory * gss * (1.0 - time)
+    }
+
+    pub fn spread(&self, time: f64) -> f64 {
+        let gss = self.gamma * self.volatility * self.volatility;
+
+        gss * (1.0 - time) + (2.0 / self.gamma) * (1.0 + self.gamma / self.k).ln()
+    }
+}
+
+impl QuotingStrategy for ExponentialUtilityStrategy {
+    fn compute(&self, time: f64, price: f64, inventory: f64) -> [f64; 2] {
+        let rp = self.reservation_price(time, price, inventory);
+        let sp = self.spread(time);
 
         [rp + sp / 2.0 - price, price - (rp - sp / 2.0)]
     }
 }
+
+/// Exponential-utility quotes that withdraw from the side pushing inventory towards its limit.
+///
+/// Beyond zero inventory, each unit of exposure widens the offending side by `-ln(1 - u) / k`,
+/// where `u` is the fraction of the bound consumed; under exponential fill intensities this
+/// scales the fill rate by `1 - u`, reaching zero (an infinite offset) at the bound.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BoundedInventoryStrategy {
+    inner: ExponentialUtilityStrategy,
+    inv_bounds: [f64; 2],
+}
+
+impl BoundedInventoryStrategy {
+    pub fn new(k: f64, gamma: f64, volatility: f64,
+               inv_bounds: [f64; 2]) -> BoundedInventoryStrategy
+    {
+        // A zero bound would make every exposure on that side an infinite utilisation:
+        assert!(inv_bounds[0] < 0.0 && inv_bounds[1] > 0.0,
+                "inventory bounds must straddle zero, got {:?}", inv_bounds);
+
+        BoundedInventoryStrategy {
+            inner: ExponentialUtilityStrategy::new(k, gamma, volatility),
+            inv_bounds,
+        }
+    }
+
+    fn withdrawal(&self, utilisation: f64) -> f64 {
+        if utilisation >= 1.0 {
+            f64::INFINITY
+        } else {
+            -(1.0 - utilisation.max(0.0)).ln() / self.inner.k
+        }
+    }
+}
+
+impl QuotingStrategy for BoundedInventoryStrategy {
+    fn compute(&self, time: f64, price: f64, inventory: f64) -> [f64; 2] {
+        let [ask, bid] = self.inner.compute(time, price, inventory);
+
+        [
+            ask + self.withdrawal(inventory / self.inv_bounds[0]),
+            bid + self.withdrawal(inventory / self.inv_bounds[1]),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const K: f64 = 1.5;
+    const GAMMA: f64 = 0.1;
+    const VOLATILITY: f64 = 2.0;
+    const ETA: f64 = 0.05;
+
+    const PRICE: f64 = 100.0;
+    const TIMES: [f64; 3] = [0.0, 0.5, 0.99];
+    const INVENTORIES: [f64; 4] = [1.0, 2.0, 3.0, 4.0];
+
+    // Checks the quote shape documented on `QuotingStrategy`, given the spread expected at each
+    // time and inventory:
+    fn assert_quote_shape<S, F>(strategy: &S, spread: F)
+    where
+        S: QuotingStrategy,
+        F: Fn(f64, f64) -> f64,
+    {
+        for &t in TIMES.iter() {
+            let [ask, bid] = strategy.compute(t, PRICE, 0.0);
+
+            assert!((ask - bid).abs() < 1e-12, "asymmetric at t = {}", t);
+
+            for &q in INVENTORIES.iter() {
+                let [ask, bid] = strategy.compute(t, PRICE, q);
+
+                assert!(ask <= bid, "long {} at t = {} quotes {} / {}", q, t, ask, bid);
+                assert!((ask + bid - spread(t, q)).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn quotes_are_symmetric_when_flat_and_skewed_when_long() {
+        let exp = ExponentialUtilityStrategy::new(K, GAMMA, VOLATILITY);
+        let bounded = BoundedInventoryStrategy::new(K, GAMMA, VOLATILITY, [-10.0, 10.0]);
+
+        // Risk-neutral quotes ignore inventory, so the skew is only weak:
+        assert_quote_shape(&LinearUtilityStrategy::new(K), |_, _| 2.0 / K);
+        assert_quote_shape(
+            &LinearUtilityTerminalPenaltyStrategy::new(K, ETA), |_, _| 2.0 / K + 2.0 * ETA,
+        );
+        assert_quote_shape(&exp, |t, _| exp.spread(t));
+
+        // A long position only withdraws the bid:
+        assert_quote_shape(&bounded, |t, q| exp.spread(t) + bounded.withdrawal(q / 10.0));
+    }
+
+    #[test]
+    fn terminal_penalty_shifts_the_reservation_price_by_two_eta_q() {
+        let strategy = LinearUtilityTerminalPenaltyStrategy::new(K, ETA);
+
+        for &q in [-10.0, -3.0, 0.0, 1.0, 7.5].iter() {
+            let [ask, bid] = strategy.compute(0.5, PRICE, q);
+
+            // The reservation price is the midpoint of the quoted ask and bid prices:
+            let rp = ((PRICE + ask) + (PRICE - bid)) / 2.0;
+
+            assert!((ask + bid - (2.0 / K + 2.0 * ETA)).abs() < 1e-12);
+            assert!((rp - PRICE + 2.0 * ETA * q).abs() < 1e-12, "reservation {} at q = {}", rp, q);
+        }
+    }
+
+    #[test]
+    fn bounded_inventory_withdraws_at_the_bounds() {
+        let inner = ExponentialUtilityStrategy::new(K, GAMMA, VOLATILITY);
+        let strategy = BoundedInventoryStrategy::new(K, GAMMA, VOLATILITY, [-5.0, 10.0]);
+
+        // Flat inventory leaves the inner quotes untouched:
+        assert_eq!(strategy.compute(0.5, 100.0, 0.0), inner.compute(0.5, 100.0, 0.0));
+
+        // Half of the long bound halves the bid fill rate, and the ask is left alone:
+        let [ask, bid] = strategy.compute(0.5, 100.0, 5.0);
+        let [inner_ask, inner_bid] = inner.compute(0.5, 100.0, 5.0);
+
+        assert_eq!(ask, inner_ask);
+        assert!((bid - inner_bid - 2f64.ln() / K).abs() < 1e-12);
+
+        // At or beyond a bound, the side that would breach it is withdrawn:
+        assert_eq!(strategy.compute(0.5, 100.0, 10.0)[1], f64::INFINITY);
+        assert_eq!(strategy.compute(0.5, 100.0, 12.0)[1], f64::INFINITY);
+        assert_eq!(strategy.compute(0.5, 100.0, -5.0)[0], f64::INFINITY);
+        assert!(strategy.compute(0.5, 100.0, -4.99)[0].is_finite());
+        assert!(strategy.compute(0.5, 100.0, -5.0)[1].is_finite());
+    }
+
+    #[test]
+    #[should_panic]
+    fn bounded_inventory_rejects_a_zero_bound() {
+        BoundedInventoryStrategy::new(K, GAMMA, VOLATILITY, [0.0, 10.0]);
+    }
+}
@@ -1,5 +1,11 @@
-use crate::dynamics::{ASDynamics, PriceDynamics, ExecutionDynamics, PoissonRate, BrownianMotion};
-use rand::thread_rng;
+use crate::{
+    dynamics::{ASDynamics, PriceDynamics, ExecutionDynamics, PoissonRate, BrownianMotion},
+    strategies::{QuotingStrategy, ExponentialUtilityStrategy},
+    utils::softplus,
+};
+use rand::{Rng, distributions::Distribution, rngs::{StdRng, ThreadRng}, thread_rng};
+use rand_distr::Exp1;
+use std::{fmt, sync::Arc};
 use rsrl::{
     domains::{Domain, Transition, Observation},
     spaces::{
@@ -10,19 +16,143 @@ use rsrl::{
 
 const INV_BOUNDS: [f64; 2] = [-50.0, 50.0];
 
-#[derive(Debug)]
-pub struct TraderDomain<P, E> {
-    pub dynamics: ASDynamics<P, E>,
+/// How the per-step reward of a `TraderDomain` is formed.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum RewardMode {
+    /// Cash from fills plus the mark-to-market change of the inventory, less penalties.
+    #[default]
+    AdditivePnl,
+
+    /// The log-return `ln(V_t / V_{t-1})` of the portfolio value tracked by `with_log_wealth`, so
+    /// that the summed rewards are the log growth of the episode. It replaces the cashflow reward
+    /// entirely, terminal inventory penalty included.
+    LogWealth,
+}
+
+/// Terminal time of a `TraderDomain` episode, drawn afresh at construction and on every reset.
+///
+/// A random horizon hides the liquidation time from the agent, which observes only the elapsed
+/// time. Fixed horizons draw nothing, so they leave the dynamics' random stream untouched.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Horizon {
+    Fixed(f64),
+
+    /// Exponentially distributed with the given mean.
+    Exponential(f64),
+
+    /// Uniform on `[low, high)`.
+    Uniform(f64, f64),
+}
+
+impl Default for Horizon {
+    fn default() -> Horizon { Horizon::Fixed(1.0) }
+}
+
+impl Distribution<f64> for Horizon {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        match *self {
+            Horizon::Fixed(t) => t,
+            Horizon::Exponential(mean) => {
+                let e: f64 = rng.sample(Exp1);
+
+                mean * e
+            },
+            Horizon::Uniform(low, high) => rng.gen_range(low, high),
+        }
+    }
+}
+
+/// View of a `TraderDomain` passed to its reward shaper, taken after each step.
+#[derive(Clone, Copy, Debug)]
+pub struct TraderDomainState {
+    pub time: f64,
+    pub price: f64,
+    pub inv: f64,
+    pub wealth: f64,
+}
+
+// Shared rather than boxed, so that domains stay `Clone` and `Send`:
+#[derive(Clone)]
+struct RewardShaper(Arc<dyn Fn(&TraderDomainState) -> f64 + Send + Sync>);
+
+impl fmt::Debug for RewardShaper {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { f.write_str("RewardShaper") }
+}
+
+/// Cloning copies the dynamics' RNG along with the rest of the state. A clone of a seeded
+/// (`StdRng`) domain replays exactly the same randomness as the original, so branches differ only
+/// through their actions; call `dynamics.reseed` on a branch to make it diverge reproducibly. A
+/// `ThreadRng` clone instead shares the thread's generator, so its branches diverge at once.
+#[derive(Clone, Debug)]
+pub struct TraderDomain<P, E, R = ThreadRng> {
+    pub dynamics: ASDynamics<P, E, R>,
 
     pub inv: f64,
     pub inv_terminal: f64,
 
     pub reward: f64,
+    /// Cash from fills plus the terminal liquidation at the mid. Over an episode without reward
+    /// normalisation, the summed rewards plus the terminal inventory penalty equal this value.
     pub wealth: f64,
 
-    eta: f64,
+    eta_long: f64,
+    eta_short: f64,
+    lot_size: f64,
+    max_fills_per_step: Option<usize>,
+    inv_bounds: [f64; 2],
+    quote_margin: Option<f64>,
+    normalize_reward: bool,
+    normalize_state: bool,
+    observe_price: bool,
+    observe_wealth: bool,
+    wealth_scale: f64,
+    liquidation_steps: usize,
+    max_offset: f64,
+    offset_penalty: Option<f64>,
+    squash_actions: bool,
+    clamped_actions: usize,
+    reward_mode: RewardMode,
+    reward_shaper: Option<RewardShaper>,
+    horizon: Horizon,
+    terminal_time: f64,
+
+    // Starting capital, if log wealth is tracked, and the last marked portfolio value:
+    initial_capital: Option<f64>,
+    log_wealth: f64,
+    last_value: f64,
+    spread_integral: f64,
+
+    asks_quoted: usize,
+    asks_filled: usize,
+    bids_quoted: usize,
+    bids_filled: usize,
+
+    // Fills from the previous step, as (side, offset), awaiting the next mid move:
+    unassessed_fills: Vec<(f64, f64)>,
+    realized_spread_sum: f64,
+    realized_spread_count: usize,
+}
+
+/// Outcome of a single call to [`TraderDomain::step_detailed`].
+#[derive(Clone, Copy, Debug)]
+pub struct StepResult {
+    pub reward: f64,
+    /// Inventory after this step's fills, before any terminal liquidation.
+    pub inventory: f64,
+    pub price: f64,
+    /// Offsets at which the ask and bid were filled, if at all.
+    pub ask_fill: Option<f64>,
+    pub bid_fill: Option<f64>,
+    pub liquidated: bool,
 }
 
+// Seeded domains must remain movable across threads:
+const _: fn() = || {
+    fn assert_send<T: Send>() {}
+
+    assert_send::<TraderDomain<BrownianMotion, PoissonRate, StdRng>>();
+};
+
 impl Default for TraderDomain<BrownianMotion, PoissonRate> {
     fn default() -> Self {
         TraderDomain::new(ASDynamics::default(), 0.0)
@@ -41,12 +171,48 @@ impl TraderDomain<BrownianMotion, PoissonRate> {
     }
 }
 
-impl<P, E> TraderDomain<P, E>
+impl<R: Rng> TraderDomain<BrownianMotion, PoissonRate, R> {
+    pub fn reservation_price(&self, gamma: f64) -> f64 {
+        ExponentialUtilityStrategy::new(
+            self.dynamics.execution_dynamics.decay, gamma,
+            self.dynamics.price_dynamics.volatility,
+        ).reservation_price(self.dynamics.time, self.dynamics.price, self.inv)
+    }
+
+    /// Expected utility `E[-exp(-gamma * wealth_T)]` under the Avellaneda-Stoikov quotes, or `None`
+    /// away from zero inventory, where no closed form is known.
+    ///
+    /// With the ansatz `theta(s, q, t) = theta0(t) + q s - gamma sigma^2 (T - t) q^2 / 2`, the
+    /// optimal quotes at `q = 0` sit `gamma sigma^2 tau / 2 + ln(1 + gamma / k) / gamma` from the
+    /// mid, and integrating their fill terms over the remaining time `tau` gives
+    ///
+    /// `theta0 = 2A / (k + gamma) * (1 + gamma / k)^(-k / gamma) * (1 - exp(-c tau)) / c`,
+    ///
+    /// where `c = k gamma sigma^2 / 2`. Neither the inventory bounds nor the terminal penalty enter.
+    pub fn analytic_value(&self, gamma: f64) -> Option<f64> {
+        if self.inv != 0.0 { return None; }
+
+        let a = self.dynamics.execution_dynamics.scale;
+        let k = self.dynamics.execution_dynamics.decay;
+        let sigma = self.dynamics.price_dynamics.volatility;
+
+        let tau = (self.terminal_time - self.dynamics.time).max(0.0);
+        let c = k * gamma * sigma * sigma / 2.0;
+        let horizon = if c > 0.0 { (1.0 - (-c * tau).exp()) / c } else { tau };
+
+        let theta0 = 2.0 * a / (k + gamma) * (1.0 + gamma / k).powf(-k / gamma) * horizon;
+
+        Some(-(-gamma * (self.wealth + theta0)).exp())
+    }
+}
+
+impl<P, E, R> TraderDomain<P, E, R>
 where
     P: PriceDynamics,
     E: ExecutionDynamics,
+    R: Rng,
 {
-    pub fn new(dynamics: ASDynamics<P, E>, eta: f64) -> Self {
+    pub fn new(dynamics: ASDynamics<P, E, R>, eta: f64) -> Self {
         Self {
             dynamics,
 
@@ -56,59 +222,548 @@ where
             reward: 0.0,
             wealth: 0.0,
 
-            eta,
+            eta_long: eta,
+            eta_short: eta,
+            lot_size: 1.0,
+            max_fills_per_step: None,
+            inv_bounds: INV_BOUNDS,
+            quote_margin: None,
+            normalize_reward: false,
+            normalize_state: false,
+            observe_price: false,
+            observe_wealth: false,
+            wealth_scale: 1.0,
+            liquidation_steps: 1,
+            max_offset: f64::INFINITY,
+            offset_penalty: None,
+            squash_actions: false,
+            clamped_actions: 0,
+            reward_mode: RewardMode::default(),
+            reward_shaper: None,
+            horizon: Horizon::default(),
+            terminal_time: 1.0,
+            spread_integral: 0.0,
+
+            initial_capital: None,
+            log_wealth: 0.0,
+            last_value: 0.0,
+
+            asks_quoted: 0,
+            asks_filled: 0,
+            bids_quoted: 0,
+            bids_filled: 0,
+
+            unassessed_fills: Vec::with_capacity(2),
+            realized_spread_sum: 0.0,
+            realized_spread_count: 0,
         }
     }
 
-    fn do_executions(&mut self, ask_price: f64, bid_price: f64) {
-        if self.inv > INV_BOUNDS[0] {
-            if let Some(ask_offset) = self.dynamics.try_execute_ask(ask_price) {
-                self.inv -= 1.0;
-                self.reward += ask_offset;
-                self.wealth += ask_price;
+    pub fn with_asymmetric_penalty(mut self, eta_long: f64, eta_short: f64) -> Self {
+        self.eta_long = eta_long;
+        self.eta_short = eta_short;
+
+        self
+    }
+
+    pub fn with_lot_size(mut self, lot_size: f64) -> Self {
+        self.lot_size = lot_size;
+
+        self
+    }
+
+    /// Cap the units filled on each side within one step, to match a venue's throughput; a fill
+    /// that would take a side past the cap is rejected, since each fill exchanges a whole lot.
+    ///
+    /// Each side currently matches at most one order per step, the one placed `queue_delay` steps
+    /// earlier, so the cap binds once it is below `lot_size`.
+    pub fn with_max_fills_per_step(mut self, max_fills_per_step: usize) -> Self {
+        self.max_fills_per_step = Some(max_fills_per_step);
+
+        self
+    }
+
+    /// Stop quoting the side whose next fill would carry inventory outside `inv_bounds`.
+    ///
+    /// The bounds need not be symmetric, e.g. `[-5.0, 50.0]` for a tight short-selling limit.
+    pub fn with_inv_bounds(mut self, inv_bounds: [f64; 2]) -> Self {
+        assert!(inv_bounds[0] <= 0.0 && inv_bounds[1] >= 0.0, "inventory bounds must contain zero");
+
+        self.inv_bounds = inv_bounds;
+
+        self
+    }
+
+    /// Pull, i.e. quote at an infinite offset, any side whose fill would carry inventory within
+    /// `margin` of the bound it approaches, as a risk system would. The inventory guard already
+    /// rejects fills past the bounds, so only a positive margin binds.
+    pub fn with_quote_constraints(mut self, margin: f64) -> Self {
+        assert!(
+            margin >= 0.0 && margin.is_finite(),
+            "quote margin must be finite and non-negative",
+        );
+
+        self.quote_margin = Some(margin);
+
+        self
+    }
+
+    pub fn with_normalized_reward(mut self, normalize_reward: bool) -> Self {
+        self.normalize_reward = normalize_reward;
+
+        self
+    }
+
+    /// Emit inventory as a fraction of its widest bound so that both state features lie in
+    /// `[-1, 1]`.
+    pub fn with_normalized_state(mut self, normalize_state: bool) -> Self {
+        self.normalize_state = normalize_state;
+
+        self
+    }
+
+    /// Append the price's move since the start of the episode to the state, `[time, inv, dP]`.
+    ///
+    /// With a normalised state the move is in units of the price volatility.
+    pub fn with_price_observation(mut self, observe_price: bool) -> Self {
+        self.observe_price = observe_price;
+
+        self
+    }
+
+    /// Append the episode's PnL so far, `wealth` plus the inventory marked at the mid, divided by
+    /// `scale`. It follows the price move, if that is observed too.
+    pub fn with_wealth_observation(mut self, observe_wealth: bool, scale: f64) -> Self {
+        assert!(scale > 0.0, "the wealth scale must be positive");
+
+        self.observe_wealth = observe_wealth;
+        self.wealth_scale = scale;
+
+        self
+    }
+
+    /// Replace sampled fills with a threshold on the match probability; see `ASDynamics`.
+    pub fn with_deterministic_fills(mut self, deterministic_fills: bool) -> Self {
+        self.dynamics.deterministic_fills = deterministic_fills;
+
+        self
+    }
+
+    /// Map each action component through a softplus before quoting, rather than clamping it at
+    /// zero, so that every real action is a valid offset and the map stays smooth.
+    pub fn with_squashed_actions(mut self, squash_actions: bool) -> Self {
+        self.squash_actions = squash_actions;
+
+        self
+    }
+
+    /// Clamp each quoted offset to `[0, max_offset]`.
+    pub fn with_max_offset(mut self, max_offset: f64) -> Self {
+        self.max_offset = max_offset;
+        self.offset_penalty = None;
+
+        self
+    }
+
+    /// Leave offsets beyond `max_offset` in place, but deduct `penalty` per unit of excess.
+    pub fn with_max_offset_penalty(mut self, max_offset: f64, penalty: f64) -> Self {
+        self.max_offset = max_offset;
+        self.offset_penalty = Some(penalty);
+
+        self
+    }
+
+    /// Unwind inventory evenly over the final `liquidation_steps` steps instead of in one lump.
+    pub fn with_liquidation_steps(mut self, liquidation_steps: usize) -> Self {
+        self.liquidation_steps = liquidation_steps.max(1);
+
+        self
+    }
+
+    /// Track `ln(V_t / V_0)`, where `V_t` is `initial_capital` plus `wealth` plus inventory marked
+    /// at the mid. Ruin, a non-positive portfolio value, sets the log wealth to `-inf`.
+    pub fn with_log_wealth(mut self, initial_capital: f64) -> Self {
+        self.initial_capital = Some(initial_capital);
+        self.last_value = initial_capital;
+
+        self
+    }
+
+    /// Choose how step rewards are formed; `LogWealth` needs the capital base set beforehand by
+    /// `with_log_wealth`.
+    pub fn with_reward_mode(mut self, reward_mode: RewardMode) -> Self {
+        assert!(
+            reward_mode != RewardMode::LogWealth || self.initial_capital.is_some(),
+            "log-wealth rewards require with_log_wealth to be called first"
+        );
+
+        self.reward_mode = reward_mode;
+
+        self
+    }
+
+    /// Add `shaper(state)` to the reward of every step, e.g. a running inventory penalty
+    /// `|s| -eta * s.inv * s.inv`. Clones of the domain share the one closure.
+    pub fn with_reward_shaper<F>(mut self, shaper: F) -> Self
+    where
+        F: Fn(&TraderDomainState) -> f64 + Send + Sync + 'static,
+    {
+        self.reward_shaper = Some(RewardShaper(Arc::new(shaper)));
+
+        self
+    }
+
+    /// Draw the terminal time of each episode from `horizon` instead of ending at `T = 1`.
+    pub fn with_horizon(mut self, horizon: Horizon) -> Self {
+        let valid = match horizon {
+            Horizon::Fixed(t) | Horizon::Exponential(t) => t > 0.0,
+            Horizon::Uniform(low, high) => low > 0.0 && low < high,
+        };
+
+        assert!(valid, "horizons must be positive, and uniform ones non-empty");
+
+        self.horizon = horizon;
+        self.terminal_time = self.dynamics.sample(&horizon);
+
+        self
+    }
+
+    /// Terminal time of the current episode.
+    pub fn terminal_time(&self) -> f64 { self.terminal_time }
+
+    pub fn reset(&mut self) {
+        self.dynamics.reset();
+        self.terminal_time = self.dynamics.sample(&self.horizon);
+
+        self.inv = 0.0;
+        self.inv_terminal = 0.0;
+
+        self.reward = 0.0;
+        self.wealth = 0.0;
+
+        self.spread_integral = 0.0;
+
+        self.log_wealth = 0.0;
+        self.last_value = self.initial_capital.unwrap_or(0.0);
+
+        self.asks_quoted = 0;
+        self.asks_filled = 0;
+        self.bids_quoted = 0;
+        self.bids_filled = 0;
+
+        self.unassessed_fills.clear();
+        self.realized_spread_sum = 0.0;
+        self.realized_spread_count = 0;
+    }
+
+    fn do_executions(&mut self, ask_price: f64, bid_price: f64) -> (Option<f64>, Option<f64>) {
+        let mut ask_fill = None;
+        let mut bid_fill = None;
+
+        let lot_size = self.lot_size;
+
+        // The cap counts units, and each side matches at most one lot per step, so it rejects any
+        // fill of a larger lot, and does so before the order reaches the book and its impact hooks:
+        let unit_cap = self.max_fills_per_step.map_or(f64::INFINITY, |cap| cap as f64);
+        let can_fill = lot_size <= unit_cap;
+
+        // Each guard blocks only the side whose fill would push inventory past its bound, so the
+        // reducing side still fills at the boundary, and a lot that would overshoot is never
+        // split. The bid guard sees the inventory after any ask fill, hence at the upper bound
+        // both sides may fill within one step, but not at the lower. Infinite prices are pulled
+        // quotes and never reach the book:
+        if self.inv - lot_size >= self.inv_bounds[0] && ask_price.is_finite() {
+            self.asks_quoted += 1;
+
+            let fill = if can_fill { self.dynamics.try_execute_ask(ask_price) } else { None };
+
+            if let Some(ask_offset) = fill {
+                ask_fill = Some(ask_offset);
+                self.asks_filled += 1;
+                self.unassessed_fills.push((1.0, ask_offset));
+                self.inv -= lot_size;
+                self.reward += ask_offset * lot_size;
+                self.wealth += (self.dynamics.price + ask_offset) * lot_size;
             }
         }
 
-        if self.inv < INV_BOUNDS[1] {
-            if let Some(bid_offset) = self.dynamics.try_execute_bid(bid_price) {
-                self.inv += 1.0;
-                self.reward += bid_offset;
-                self.wealth -= bid_price;
+        if self.inv + lot_size <= self.inv_bounds[1] && bid_price.is_finite() {
+            self.bids_quoted += 1;
+
+            let fill = if can_fill { self.dynamics.try_execute_bid(bid_price) } else { None };
+
+            if let Some(bid_offset) = fill {
+                bid_fill = Some(bid_offset);
+                self.bids_filled += 1;
+                self.unassessed_fills.push((-1.0, bid_offset));
+                self.inv += lot_size;
+                self.reward += bid_offset * lot_size;
+                self.wealth -= (self.dynamics.price - bid_offset) * lot_size;
             }
         }
+
+        (ask_fill, bid_fill)
     }
 
-    fn update_state(&mut self, ask_offset: f64, bid_offset: f64) {
+    fn update_state(&mut self, ask_offset: f64, bid_offset: f64) -> StepResult {
         let ask_price = self.dynamics.price + ask_offset;
         let bid_price = self.dynamics.price - bid_offset;
 
-        self.reward = self.inv * self.dynamics.innovate();
+        let price_inc = self.dynamics.innovate();
 
-        self.do_executions(ask_price, bid_price);
+        self.reward = if self.normalize_reward {
+            let scale = self.dynamics.price_dynamics.volatility() * self.dynamics.dt.sqrt();
 
-        if self.is_terminal() {
+            self.inv * price_inc / scale
+        } else {
+            self.inv * price_inc
+        };
+
+        // Edge captured by last step's fills net of the subsequent mid move:
+        for (side, offset) in self.unassessed_fills.drain(..) {
+            self.realized_spread_sum += offset - side * price_inc;
+            self.realized_spread_count += 1;
+        }
+
+        let (ask_fill, bid_fill) = self.do_executions(ask_price, bid_price);
+        let liquidated = self.is_terminal();
+
+        if !liquidated && self.liquidation_steps > 1 {
+            let remaining = self.steps_remaining();
+
+            // Sell an equal share of the residual at the mid across the unwind window:
+            if remaining < self.liquidation_steps {
+                let unwind = self.inv / (remaining + 1) as f64;
+
+                self.wealth += self.dynamics.price * unwind;
+                self.inv -= unwind;
+            }
+        }
+
+        let inventory = self.inv;
+
+        if liquidated {
             // Execute market order favourably at midprice:
             self.wealth += self.dynamics.price * self.inv;
-            self.reward -= self.eta * self.inv.powi(2);
+            self.reward -= self.eta_long * self.inv.max(0.0).powi(2)
+                + self.eta_short * self.inv.min(0.0).powi(2);
 
             self.inv_terminal = self.inv;
             self.inv = 0.0;
         }
+
+        if let Some(capital) = self.initial_capital {
+            let value = capital + self.wealth + self.inv * self.dynamics.price;
+
+            if self.reward_mode == RewardMode::LogWealth {
+                // Values are floored just above zero, so that ruin costs a large but finite reward
+                // and later steps earn nothing, rather than feeding -inf to the critic:
+                let floor = (capital.abs() * f64::EPSILON).max(f64::MIN_POSITIVE);
+
+                self.reward = (value.max(floor) / self.last_value.max(floor)).ln();
+            }
+
+            self.log_wealth = if self.last_value > 0.0 && value > 0.0 {
+                self.log_wealth + (value / self.last_value).ln()
+            } else {
+                f64::NEG_INFINITY
+            };
+            self.last_value = value;
+        }
+
+        StepResult {
+            reward: self.reward,
+            inventory,
+            price: self.dynamics.price,
+            ask_fill,
+            bid_fill,
+            liquidated,
+        }
+    }
+
+    /// Advance the domain by one step, returning the full outcome rather than an rsrl transition.
+    pub fn step_detailed(&mut self, action: [f64; 2]) -> StepResult {
+        let action = if self.squash_actions {
+            [softplus(action[0]), softplus(action[1])]
+        } else {
+            action
+        };
+
+        // Negative offsets pass through and cross the spread; only the `max_offset` cap clamps:
+        let mut offsets = action;
+        let excess = (offsets[0] - self.max_offset).max(0.0) + (offsets[1] - self.max_offset).max(0.0);
+
+        if self.offset_penalty.is_none() {
+            offsets = [offsets[0].min(self.max_offset), offsets[1].min(self.max_offset)];
+        }
+
+        if offsets != action { self.clamped_actions += 1; }
+
+        self.spread_integral += (offsets[0] + offsets[1]) * self.dynamics.dt;
+
+        // Pulled sides are left out of the spread integral above, which tracks the agent's quotes:
+        if let Some(margin) = self.quote_margin {
+            let [low, high] = self.inv_bounds;
+
+            if self.inv - self.lot_size < low + margin { offsets[0] = f64::INFINITY; }
+            if self.inv + self.lot_size > high - margin { offsets[1] = f64::INFINITY; }
+        }
+
+        let mut result = self.update_state(offsets[0], offsets[1]);
+
+        if let Some(penalty) = self.offset_penalty {
+            self.reward -= penalty * excess;
+            result.reward = self.reward;
+        }
+
+        if let Some(bonus) = self.reward_shaper.as_ref().map(|shaper| (shaper.0)(&self.view())) {
+            self.reward += bonus;
+            result.reward = self.reward;
+        }
+
+        result
+    }
+
+    /// The current state as handed to the reward shaper.
+    pub fn view(&self) -> TraderDomainState {
+        TraderDomainState {
+            time: self.dynamics.time,
+            price: self.dynamics.price,
+            inv: self.inv,
+            wealth: self.wealth,
+        }
+    }
+
+    fn is_terminal(&self) -> bool {
+        self.dynamics.time >= self.terminal_time || self.dynamics.price_dynamics.is_exhausted()
+    }
+
+    /// Offsets maximising each side's expected spread capture over the next step alone, ignoring
+    /// inventory risk; a baseline against which to judge learned quotes.
+    pub fn greedy_myopic_action(&self) -> [f64; 2] {
+        let offset = self.dynamics.execution_dynamics.myopic_offset(self.dynamics.time);
+        let offset = offset.min(self.max_offset);
+
+        [offset, offset]
+    }
+
+    /// Offsets the given strategy would quote in the current state.
+    pub fn analytic_action<S: QuotingStrategy + ?Sized>(&self, strategy: &S) -> [f64; 2] {
+        strategy.compute(self.dynamics.time, self.dynamics.price, self.inv)
     }
 
-    fn is_terminal(&self) -> bool { self.dynamics.time >= 1.0 }
+    /// State vector the domain would emit at the given time and inventory.
+    pub fn encode_state(&self, time: f64, inv: f64) -> Vec<f64> {
+        let inv = inv.clamp(self.inv_bounds[0], self.inv_bounds[1]);
+        let price_move = self.dynamics.price - self.dynamics.price_initial;
+
+        let mut state = if self.normalize_state {
+            vec![time, inv / self.inv_scale()]
+        } else {
+            vec![time, inv]
+        };
+
+        if self.observe_price {
+            state.push(if self.normalize_state {
+                price_move / self.dynamics.price_dynamics.volatility()
+            } else {
+                price_move
+            });
+        }
+
+        if self.observe_wealth {
+            state.push((self.wealth + self.inv * self.dynamics.price) / self.wealth_scale);
+        }
+
+        state
+    }
+
+    fn inv_scale(&self) -> f64 { self.inv_bounds[1].max(-self.inv_bounds[0]) }
+
+    pub fn inv_bounds(&self) -> [f64; 2] { self.inv_bounds }
+
+    /// Number of steps in the current episode, e.g. for choosing a discount with a matching
+    /// horizon. Under a random `Horizon` this changes on every reset.
+    pub fn horizon_steps(&self) -> usize {
+        (self.terminal_time / self.dynamics.dt).round() as usize
+    }
+
+    fn steps_remaining(&self) -> usize {
+        ((self.terminal_time - self.dynamics.time) / self.dynamics.dt).round().max(0.0) as usize
+    }
+
+    pub fn time_weighted_avg_spread(&self) -> f64 {
+        if self.dynamics.time > 0.0 {
+            self.spread_integral / self.dynamics.time
+        } else {
+            0.0
+        }
+    }
+
+    /// Log growth of the portfolio so far, or `None` unless enabled via `with_log_wealth`.
+    pub fn log_wealth(&self) -> Option<f64> {
+        self.initial_capital.map(|_| self.log_wealth)
+    }
+
+    pub fn asks_quoted(&self) -> usize { self.asks_quoted }
+
+    pub fn asks_filled(&self) -> usize { self.asks_filled }
+
+    pub fn bids_quoted(&self) -> usize { self.bids_quoted }
+
+    pub fn bids_filled(&self) -> usize { self.bids_filled }
+
+    pub fn fill_rate(&self) -> (f64, f64) {
+        let rate = |filled: usize, quoted: usize| if quoted > 0 {
+            filled as f64 / quoted as f64
+        } else {
+            0.0
+        };
+
+        (rate(self.asks_filled, self.asks_quoted), rate(self.bids_filled, self.bids_quoted))
+    }
+
+    pub fn realized_spread(&self) -> f64 {
+        if self.realized_spread_count > 0 {
+            self.realized_spread_sum / self.realized_spread_count as f64
+        } else {
+            0.0
+        }
+    }
+
+    /// Loose per-step reward interval, ignoring any queueing delay.
+    pub fn reward_bounds(&self) -> (f64, f64) {
+        let inv_max = self.inv_scale() + self.lot_size;
+
+        let holding = if self.normalize_reward {
+            inv_max * self.dynamics.increment_bound()
+                / (self.dynamics.price_dynamics.volatility() * self.dynamics.dt.sqrt())
+        } else {
+            inv_max * self.dynamics.increment_bound()
+        };
+        let spread = 2.0 * self.dynamics.execution_dynamics.max_offset() * self.lot_size;
+        let penalty = self.eta_long.max(self.eta_short) * inv_max.powi(2);
+
+        (-holding - penalty, holding + spread)
+    }
+
+    /// Steps whose quotes were clamped to `max_offset`, counted since the last reset.
+    pub fn clamped_actions(&self) -> usize { self.clamped_actions }
+
+    pub fn reset_clamped_actions(&mut self) { self.clamped_actions = 0; }
 }
 
-impl<P, E> Domain for TraderDomain<P, E>
+impl<P, E, R> Domain for TraderDomain<P, E, R>
 where
     P: PriceDynamics,
     E: ExecutionDynamics,
+    R: Rng,
 {
     type StateSpace = ProductSpace<Interval>;
     type ActionSpace = TwoSpace<Reals>;
 
     fn emit(&self) -> Observation<Vec<f64>> {
-        let state = vec![self.dynamics.time, self.inv.min(INV_BOUNDS[1]).max(INV_BOUNDS[0])];
+        let state = self.encode_state(self.dynamics.time, self.inv);
 
         if self.is_terminal() {
             Observation::Terminal(state)
@@ -119,24 +774,221 @@ where
 
     fn step(&mut self, action: [f64; 2]) -> Transition<Vec<f64>, [f64; 2]> {
         let from = self.emit();
-
-        self.update_state(action[0], action[1]);
+        let result = self.step_detailed(action);
 
         Transition {
             from,
             action,
             to: self.emit(),
-            reward: self.reward// - self.eta * self.inv.powi(2),
+            reward: result.reward,
         }
     }
 
     fn state_space(&self) -> Self::StateSpace {
-        ProductSpace::empty()
-            + Interval::bounded(0.0, 1.0)
-            + Interval::bounded(INV_BOUNDS[0], INV_BOUNDS[1])
+        let inv_space = if self.normalize_state {
+            let scale = self.inv_scale();
+
+            Interval::bounded(self.inv_bounds[0] / scale, self.inv_bounds[1] / scale)
+        } else {
+            Interval::bounded(self.inv_bounds[0], self.inv_bounds[1])
+        };
+
+        let time_space = Interval::bounded(0.0, self.terminal_time);
+        let space = ProductSpace::empty() + time_space + inv_space;
+
+        let space = if self.observe_price { space + Interval::unbounded() } else { space };
+
+        if self.observe_wealth { space + Interval::unbounded() } else { space }
     }
 
     fn action_space(&self) -> TwoSpace<Reals> {
         TwoSpace::new([Reals; 2])
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dynamics::ConstantRate;
+    use rand::SeedableRng;
+
+    const DT: f64 = 0.005;
+
+    // Every quoted order fills, so the only randomness left is in the price path:
+    fn always_fill(seed: u64) -> TraderDomain<BrownianMotion, ConstantRate, StdRng> {
+        let dynamics = ASDynamics::new(
+            DT, 100.0, StdRng::seed_from_u64(seed),
+            BrownianMotion::new(DT, 2.0), ConstantRate::new(1.0),
+        );
+
+        TraderDomain::new(dynamics, 0.0)
+    }
+
+    // A pulled ask, so that only the bid can fill:
+    const BID_ONLY: [f64; 2] = [f64::INFINITY, 1.0];
+    const ASK_ONLY: [f64; 2] = [1.0, f64::INFINITY];
+
+    #[test]
+    fn negative_offsets_pass_through_unclamped() {
+        let mut domain = always_fill(0).with_lot_size(1.0);
+        let result = domain.step_detailed([f64::INFINITY, -0.5]);
+
+        // The bid is placed above the mid, and the trader pays for crossing the spread:
+        assert!(result.bid_fill.expect("the bid always fills") < 0.0);
+        assert_eq!(domain.clamped_actions(), 0);
+    }
+
+    #[test]
+    fn fill_cap_rejects_fills_without_splitting_lots() {
+        let mut capped = always_fill(0).with_lot_size(10.0).with_max_fills_per_step(10);
+        let mut blocked = always_fill(0).with_lot_size(10.0).with_max_fills_per_step(5);
+
+        for _ in 0..5 {
+            assert!(capped.step_detailed([1.0, 1.0]).ask_fill.is_some());
+
+            // Five units a step cannot take a lot of ten, which is never split to fit:
+            let result = blocked.step_detailed([1.0, 1.0]);
+
+            assert_eq!((result.ask_fill, result.bid_fill), (None, None));
+        }
+
+        assert_eq!(capped.asks_filled(), 5);
+        assert_eq!(capped.bids_filled(), 5);
+
+        // A single fill moves a whole lot:
+        capped.step_detailed(BID_ONLY);
+        assert_eq!(capped.inv, 10.0);
+
+        assert_eq!(blocked.asks_filled() + blocked.bids_filled(), 0);
+        assert_eq!(blocked.inv, 0.0);
+
+        // Unit lots fill under any cap but zero:
+        let mut unit = always_fill(0).with_max_fills_per_step(1);
+        let mut halted = always_fill(0).with_max_fills_per_step(0);
+
+        assert!(unit.step_detailed(BID_ONLY).bid_fill.is_some());
+        assert!(halted.step_detailed(BID_ONLY).bid_fill.is_none());
+    }
+
+    #[test]
+    fn lots_never_overshoot_the_bounds() {
+        // Three lots of 2 would carry inventory from 0 to -6, past the bound:
+        let mut domain = always_fill(0).with_lot_size(2.0).with_inv_bounds([-5.0, 5.0]);
+
+        for _ in 0..5 { domain.step_detailed(ASK_ONLY); }
+
+        assert_eq!(domain.inv, -4.0);
+
+        // The side that reduces the position still fills:
+        domain.step_detailed(BID_ONLY);
+        assert_eq!(domain.inv, -2.0);
+
+        // Fractional lots stop at the last multiple inside the bound:
+        let mut domain = always_fill(0).with_lot_size(0.3).with_inv_bounds([-1.0, 1.0]);
+
+        for _ in 0..10 {
+            domain.step_detailed(BID_ONLY);
+
+            assert!(domain.inv <= 1.0);
+        }
+
+        assert!((domain.inv - 0.9).abs() < 1e-12);
+    }
+
+    #[test]
+    fn quote_constraints_pull_quotes_inside_the_margin() {
+        let mut free = always_fill(0).with_inv_bounds([-5.0, 5.0]);
+        let mut constrained = always_fill(0)
+            .with_inv_bounds([-5.0, 5.0])
+            .with_quote_constraints(2.0);
+
+        for _ in 0..3 {
+            free.step_detailed(BID_ONLY);
+            constrained.step_detailed(BID_ONLY);
+        }
+
+        assert_eq!(free.inv, 3.0);
+        assert_eq!(constrained.inv, 3.0);
+
+        // A fourth lot stays inside the bounds, but not the margin:
+        assert!(free.step_detailed(BID_ONLY).bid_fill.is_some());
+        assert!(constrained.step_detailed(BID_ONLY).bid_fill.is_none());
+        assert_eq!(constrained.inv, 3.0);
+
+        // The reducing side is left quoting:
+        assert!(constrained.step_detailed(ASK_ONLY).ask_fill.is_some());
+    }
+
+    #[test]
+    fn asymmetric_bounds_cap_each_side_separately() {
+        let mut domain = always_fill(0).with_inv_bounds([-5.0, 50.0]);
+
+        for _ in 0..20 {
+            domain.step_detailed(ASK_ONLY);
+
+            assert!(domain.inv >= -5.0);
+        }
+
+        assert_eq!(domain.inv, -5.0);
+
+        for _ in 0..80 {
+            domain.step_detailed(BID_ONLY);
+
+            assert!(domain.inv <= 50.0);
+        }
+
+        assert_eq!(domain.inv, 50.0);
+    }
+
+    #[test]
+    fn wealth_matches_trade_cashflows_and_summed_reward() {
+        const ETA: f64 = 0.01;
+
+        let mut domain = TraderDomain::new(always_fill(7).dynamics, ETA);
+        let actions = [
+            [f64::INFINITY, 0.4], [0.6, 0.2], [0.5, f64::INFINITY],
+            [f64::INFINITY, 0.1], [0.3, 0.3],
+        ];
+
+        let mut cash = 0.0;
+        let mut reward_sum = 0.0;
+
+        for i in 0.. {
+            let action = actions[i % actions.len()];
+            let result = domain.step_detailed(action);
+
+            // Every quoted side fills, a unit lot each, at an offset from the post-move mid:
+            assert_eq!(result.ask_fill.is_some(), action[0].is_finite());
+            assert_eq!(result.bid_fill.is_some(), action[1].is_finite());
+
+            if let Some(offset) = result.ask_fill { cash += result.price + offset; }
+            if let Some(offset) = result.bid_fill { cash -= result.price - offset; }
+
+            reward_sum += result.reward;
+
+            if result.liquidated {
+                cash += result.price * result.inventory;
+
+                break
+            }
+        }
+
+        let penalty = ETA * domain.inv_terminal.powi(2);
+
+        assert_ne!(domain.inv_terminal, 0.0);
+        assert!((domain.wealth - cash).abs() < 1e-9 * cash.abs().max(1.0));
+        assert!((domain.wealth - reward_sum - penalty).abs() < 1e-9 * cash.abs().max(1.0));
+    }
+
+    #[test]
+    fn fills_move_inventory_by_the_lot_size() {
+        let mut domain = always_fill(0).with_lot_size(10.0);
+        let result = domain.step_detailed(BID_ONLY);
+
+        let offset = result.bid_fill.expect("the bid always fills");
+
+        assert_eq!(domain.inv, 10.0);
+        assert_eq!(domain.wealth, -(result.price - offset) * 10.0);
+        assert_eq!(domain.reward, offset * 10.0);
+    }
+}
@@ -1,11 +1,17 @@
+use std::{collections::VecDeque, fmt};
+
+/// Mean and population variance, both zero for an empty slice.
 pub fn mean_var(values: &[f64]) -> [f64; 2] {
+    if values.is_empty() { return [0.0, 0.0]; }
+
     let n = values.len() as f64;
 
-    let sum: f64 = values.iter().sum();
-    let sumsq: f64 = values.iter().fold(0.0, |acc, v| acc + v * v);
+    // Summing deviations from the first value keeps the mean of a constant slice exact:
+    let shift = values[0];
+    let mean = shift + values.iter().map(|v| v - shift).sum::<f64>() / n;
 
-    let mean = sum / n;
-    let var = sumsq / n - mean * mean;
+    // Two passes, since `E[x^2] - E[x]^2` cancels badly and can even go negative:
+    let var = values.iter().fold(0.0, |acc, v| acc + (v - mean).powi(2)) / n;
 
     [mean, var]
 }
@@ -16,6 +22,141 @@ pub fn median_quantiles(values: &[f64]) -> [f64; 3] {
     [values[pivot], values[pivot * 2], values[pivot * 3]]
 }
 
+// `total_cmp` orders any NaN after every number, so a NaN is never taken as a worst outcome:
+fn sort_ascending(returns: &mut [f64]) {
+    returns.sort_unstable_by(f64::total_cmp);
+}
+
+/// Value-at-Risk: the `alpha`-quantile of `returns`, i.e. the threshold of the worst outcomes.
+///
+/// Returns `None` for an empty slice.
+pub fn var(returns: &mut [f64], alpha: f64) -> Option<f64> {
+    if returns.is_empty() { return None; }
+
+    sort_ascending(returns);
+
+    let i = ((alpha * returns.len() as f64).floor() as usize).min(returns.len() - 1);
+
+    Some(returns[i])
+}
+
+/// Conditional Value-at-Risk: the mean of the worst `alpha`-fraction of `returns`.
+///
+/// Returns `None` for an empty slice.
+pub fn cvar(returns: &mut [f64], alpha: f64) -> Option<f64> {
+    if returns.is_empty() { return None; }
+
+    sort_ascending(returns);
+
+    let n = ((alpha * returns.len() as f64).ceil() as usize).clamp(1, returns.len());
+
+    Some(returns[..n].iter().sum::<f64>() / n as f64)
+}
+
+/// Map a (reservation shift, half-spread) pair to `[ask_offset, bid_offset]`.
+///
+/// Quotes are centred on `mid + reservation`, so a positive shift moves both the ask and bid up:
+/// `ask = mid + (reservation + half_spread)` and `bid = mid - (half_spread - reservation)`.
+pub fn to_offsets(reservation: f64, half_spread: f64) -> [f64; 2] {
+    [reservation + half_spread, half_spread - reservation]
+}
+
+/// Relative (log) volatility matching an absolute volatility at the given price level.
+///
+/// Arithmetic (`dS = abs_vol dW`) and geometric (`dS / S = rel_vol dW`) Brownian motion agree
+/// locally when `abs_vol = rel_vol * S`; away from `price` the two models diverge.
+pub fn abs_to_rel_vol(abs_vol: f64, price: f64) -> f64 { abs_vol / price }
+
+/// Absolute volatility matching a relative (log) volatility at the given price level; see
+/// `abs_to_rel_vol`.
+pub fn rel_to_abs_vol(rel_vol: f64, price: f64) -> f64 { rel_vol * price }
+
+/// `ln(1 + e^x)`, computed without overflow for large `x`.
+pub fn softplus(x: f64) -> f64 {
+    x.max(0.0) + (-x.abs()).exp().ln_1p()
+}
+
+/// Inverse of `to_offsets`, returning `(reservation, half_spread)`.
+pub fn from_offsets(ask_offset: f64, bid_offset: f64) -> (f64, f64) {
+    ((ask_offset - bid_offset) / 2.0, (ask_offset + bid_offset) / 2.0)
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ExponentialMovingAverage {
+    alpha: f64,
+    value: Option<f64>,
+}
+
+impl ExponentialMovingAverage {
+    pub fn new(alpha: f64) -> Self {
+        ExponentialMovingAverage { alpha, value: None, }
+    }
+
+    pub fn update(&mut self, x: f64) -> f64 {
+        let value = match self.value {
+            Some(v) => self.alpha * x + (1.0 - self.alpha) * v,
+            None => x,
+        };
+
+        self.value = Some(value);
+
+        value
+    }
+
+    pub fn value(&self) -> Option<f64> { self.value }
+}
+
+/// Rolling estimate of volatility from an observed price path.
+///
+/// Prices in this crate follow arithmetic dynamics, so the window holds price increments rather
+/// than log-returns; the estimate is in the same units as `PriceDynamics::volatility`.
+#[derive(Clone, Debug)]
+pub struct RealizedVolEstimator {
+    dt: f64,
+    window: usize,
+    last_price: Option<f64>,
+    increments: VecDeque<f64>,
+}
+
+impl RealizedVolEstimator {
+    pub fn new(dt: f64, window: usize) -> Self {
+        RealizedVolEstimator {
+            dt,
+            window,
+            last_price: None,
+            increments: VecDeque::with_capacity(window),
+        }
+    }
+
+    pub fn push(&mut self, price: f64) {
+        if let Some(last) = self.last_price {
+            if self.increments.len() == self.window { self.increments.pop_front(); }
+
+            self.increments.push_back(price - last);
+        }
+
+        self.last_price = Some(price);
+    }
+
+    pub fn reset(&mut self) {
+        self.last_price = None;
+        self.increments.clear();
+    }
+
+    /// Returns `None` until at least two increments have been observed.
+    pub fn volatility(&self) -> Option<f64> {
+        let n = self.increments.len();
+
+        if n < 2 { return None; }
+
+        let mean = self.increments.iter().sum::<f64>() / n as f64;
+        let var = self.increments.iter()
+            .fold(0.0, |acc, x| acc + (x - mean).powi(2)) / (n - 1) as f64;
+
+        Some((var / self.dt).sqrt())
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct Estimate(pub f64, pub f64);
 
@@ -27,6 +168,49 @@ impl Estimate {
     }
 }
 
+/// Streaming mean and (population) variance via Welford's algorithm.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RunningEstimate {
+    count: usize,
+    mean: f64,
+    m2: f64,
+}
+
+impl RunningEstimate {
+    pub fn new() -> RunningEstimate { RunningEstimate::default() }
+
+    pub fn push(&mut self, x: f64) {
+        self.count += 1;
+
+        let delta = x - self.mean;
+
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    pub fn count(&self) -> usize { self.count }
+
+    pub fn mean(&self) -> f64 { self.mean }
+
+    pub fn variance(&self) -> f64 {
+        if self.count > 0 { self.m2 / self.count as f64 } else { 0.0 }
+    }
+
+    /// Same statistics as `Estimate::from_slice` over the values pushed so far.
+    pub fn estimate(&self) -> Estimate { Estimate(self.mean, self.variance().sqrt()) }
+}
+
+/// Formats as `"{mean} ± {stddev}"`, applying any requested precision to both terms.
+impl fmt::Display for Estimate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match f.precision() {
+            Some(p) => write!(f, "{:.*} ± {:.*}", p, self.0, p, self.1),
+            None => write!(f, "{} ± {}", self.0, self.1),
+        }
+    }
+}
+
+#[cfg(feature = "logging")]
 impl slog::Value for Estimate {
     fn serialize(
         &self,
@@ -35,6 +219,104 @@ impl slog::Value for Estimate {
         serializer: &mut dyn slog::Serializer
     ) -> slog::Result
     {
-        serializer.emit_arguments(key, &format_args!("{} ± {}", self.0, self.1))
+        serializer.emit_arguments(key, &format_args!("{}", self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The integers 1 to 100, shuffled, with a NaN that must sort past them:
+    fn sample() -> Vec<f64> {
+        let mut xs: Vec<f64> = (1..=100).map(|i| ((i * 37) % 101) as f64).collect();
+
+        xs.push(f64::NAN);
+        xs
+    }
+
+    #[test]
+    fn var_is_the_alpha_quantile() {
+        assert_eq!(var(&mut sample(), 0.05), Some(6.0));
+        assert_eq!(var(&mut sample(), 0.0), Some(1.0));
+        assert_eq!(var(&mut sample(), 0.5), Some(51.0));
+        assert_eq!(var(&mut [3.0], 0.05), Some(3.0));
+        assert_eq!(var(&mut [], 0.05), None);
+    }
+
+    #[test]
+    fn cvar_is_the_mean_of_the_worst_outcomes() {
+        let mut xs: Vec<f64> = sample().into_iter().filter(|x| !x.is_nan()).collect();
+
+        assert_eq!(cvar(&mut xs, 0.05), Some(3.0));
+        assert_eq!(cvar(&mut xs, 0.1), Some(5.5));
+        assert_eq!(cvar(&mut xs, 1.0), Some(50.5));
+
+        // At least one outcome is always averaged:
+        assert_eq!(cvar(&mut xs, 0.0), Some(1.0));
+        assert_eq!(cvar(&mut [], 0.05), None);
+    }
+
+    #[test]
+    fn mean_var_handles_degenerate_samples() {
+        assert_eq!(mean_var(&[]), [0.0, 0.0]);
+        assert_eq!(mean_var(&[-3.5]), [-3.5, 0.0]);
+
+        // One pass of `E[x^2] - E[x]^2` would lose everything to cancellation here:
+        assert_eq!(mean_var(&[1e9 + 0.1; 1000]), [1e9 + 0.1, 0.0]);
+
+        let [_, var] = mean_var(&[1e8 + 1.0, 1e8 + 2.0, 1e8 + 3.0]);
+
+        assert!((var - 2.0 / 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn vol_conversions_invert_each_other_at_the_reference_price() {
+        assert_eq!(abs_to_rel_vol(2.0, 100.0), 0.02);
+        assert_eq!(rel_to_abs_vol(0.02, 100.0), 2.0);
+        assert_eq!(abs_to_rel_vol(0.0, 50.0), 0.0);
+
+        for &(vol, price) in [(2.0, 100.0), (0.3, 7.5), (15.0, 1e4), (1e-3, 0.01)].iter() {
+            let rel = abs_to_rel_vol(vol, price);
+
+            assert!((rel_to_abs_vol(rel, price) - vol).abs() < 1e-12 * vol);
+            assert!((abs_to_rel_vol(rel_to_abs_vol(rel, price), price) - rel).abs() < 1e-12 * rel);
+        }
+
+        // The same relative vol is a larger absolute vol at a higher price:
+        assert_eq!(rel_to_abs_vol(0.02, 200.0), 2.0 * rel_to_abs_vol(0.02, 100.0));
+    }
+
+    #[cfg(feature = "sim")]
+    #[test]
+    fn realized_vol_recovers_brownian_volatility() {
+        use crate::dynamics::{BrownianMotion, PriceDynamics};
+        use rand::{SeedableRng, rngs::StdRng};
+
+        const DT: f64 = 0.005;
+        const WINDOW: usize = 10_000;
+
+        let path = BrownianMotion::new(DT, 2.0)
+            .sample_path(&mut StdRng::seed_from_u64(0), 100.0, 4 * WINDOW);
+        let mut estimator = RealizedVolEstimator::new(DT, WINDOW);
+
+        estimator.push(100.0);
+        assert_eq!(estimator.volatility(), None);
+
+        estimator.push(path[0]);
+        assert_eq!(estimator.volatility(), None);
+
+        // Each full window has a standard error of about 2 / sqrt(2 * WINDOW) = 0.014:
+        for chunk in path[1..].chunks(WINDOW) {
+            for &price in chunk { estimator.push(price); }
+
+            let vol = estimator.volatility().unwrap();
+
+            assert!((vol - 2.0).abs() < 0.06, "estimated {}", vol);
+        }
+
+        estimator.reset();
+        estimator.push(100.0);
+        assert_eq!(estimator.volatility(), None);
     }
 }
@@ -1,5 +1,8 @@
-use crate::dynamics::{ASDynamics, PoissonRate, BrownianMotionWithDrift};
-use rand::thread_rng;
+use crate::dynamics::{
+    ASDynamics, ExecutionDynamics, PoissonRate, PriceDynamics, BrownianMotionWithDrift,
+    SettableDrift,
+};
+use rand::{Rng, rngs::ThreadRng, thread_rng};
 use rsrl::{
     domains::{Domain, Transition, Observation},
     spaces::{
@@ -8,16 +11,56 @@ use rsrl::{
     },
 };
 
+const MAX_DRIFT: f64 = 10.0;
+const MAX_INTENSITY_FACTOR: f64 = 4.0;
 const INV_BOUNDS: [f64; 2] = [-50.0, 50.0];
 
-pub struct ZeroSumDomain<P, E> {
-    pub dynamics: ASDynamics<P, E>,
+/// What the adversary is rewarded for in a `ZeroSumDomain`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum AdversaryObjective {
+    /// The negation of the trader's reward, making the game zero-sum.
+    #[default]
+    NegatedPnl,
+
+    /// The trader's realised mark-to-market variance, `(inv * price_inc)^2`, each step.
+    InventoryVariance,
+}
+
+/// What the adversary's action in `[0, 1]` controls in a `ZeroSumDomain`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum AdversaryControl {
+    /// The drift of the price process, from `-MAX_DRIFT` to `MAX_DRIFT`.
+    #[default]
+    Drift,
+
+    /// The arrival intensity `scale` of market orders, from a quarter to four times its value
+    /// when the control was chosen. The price is left to its own drift, so the adversary attacks
+    /// through which quotes fill, and when, rather than through where the price goes.
+    FillIntensity,
+}
+
+/// Clones share or split randomness as described for `TraderDomain`.
+#[derive(Clone)]
+pub struct ZeroSumDomain<P, E, R = ThreadRng> {
+    pub dynamics: ASDynamics<P, E, R>,
 
     pub inv: f64,
     pub inv_terminal: f64,
 
     pub reward: f64,
     pub wealth: f64,
+
+    objective: AdversaryObjective,
+    control: AdversaryControl,
+    base_scale: f64,
+    adversary_reward: f64,
+
+    // Stddev of the noise on the drift shown to the trader, if it is shown at all:
+    drift_noise: Option<f64>,
+    observed_drift: f64,
+
+    clamped_actions: usize,
+    spread_integral: f64,
 }
 
 impl Default for ZeroSumDomain<BrownianMotionWithDrift, PoissonRate> {
@@ -30,8 +73,19 @@ impl Default for ZeroSumDomain<BrownianMotionWithDrift, PoissonRate> {
     }
 }
 
-impl ZeroSumDomain<BrownianMotionWithDrift, PoissonRate> {
-    pub fn new(dynamics: ASDynamics<BrownianMotionWithDrift, PoissonRate>) -> Self {
+impl<R: Rng> ZeroSumDomain<BrownianMotionWithDrift, PoissonRate, R> {
+    /// Set the volatility of the price process, leaving its drift and step size unchanged.
+    pub fn with_volatility(mut self, volatility: f64) -> Self {
+        self.dynamics.price_dynamics.volatility = volatility;
+
+        self
+    }
+}
+
+impl<P: PriceDynamics + SettableDrift, R: Rng> ZeroSumDomain<P, PoissonRate, R> {
+    pub fn new(dynamics: ASDynamics<P, PoissonRate, R>) -> Self {
+        let base_scale = dynamics.execution_dynamics.scale;
+
         Self {
             dynamics,
 
@@ -40,15 +94,54 @@ impl ZeroSumDomain<BrownianMotionWithDrift, PoissonRate> {
 
             reward: 0.0,
             wealth: 0.0,
+
+            objective: AdversaryObjective::default(),
+            control: AdversaryControl::default(),
+            base_scale,
+            adversary_reward: 0.0,
+
+            drift_noise: None,
+            observed_drift: 0.0,
+
+            clamped_actions: 0,
+            spread_integral: 0.0,
         }
     }
 
+    pub fn with_objective(mut self, objective: AdversaryObjective) -> Self {
+        self.objective = objective;
+
+        self
+    }
+
+    /// Choose the adversary's channel of attack; `FillIntensity` scales the execution dynamics'
+    /// current `scale`.
+    pub fn with_control(mut self, control: AdversaryControl) -> Self {
+        self.control = control;
+        self.base_scale = self.dynamics.execution_dynamics.scale;
+
+        self
+    }
+
+    /// Append a noisy reading of the last drift to the trader's state, `[time, inv, drift]`.
+    ///
+    /// Each reading adds Gaussian noise with the given stddev and is clamped to the drift's
+    /// range. The adversary still observes only `[time, inv]`; see `adversary_observation`.
+    pub fn with_drift_observation(mut self, noise_stddev: f64) -> Self {
+        self.drift_noise = Some(noise_stddev);
+
+        self
+    }
+
+    /// Adversary's reward for the most recent step, according to its objective.
+    pub fn adversary_reward(&self) -> f64 { self.adversary_reward }
+
     fn do_executions(&mut self, ask_price: f64, bid_price: f64) {
         if self.inv > INV_BOUNDS[0] {
             if let Some(ask_offset) = self.dynamics.try_execute_ask(ask_price) {
                 self.inv -= 1.0;
                 self.reward += ask_offset;
-                self.wealth += ask_price;
+                self.wealth += self.dynamics.price + ask_offset;
             }
         }
 
@@ -56,20 +149,44 @@ impl ZeroSumDomain<BrownianMotionWithDrift, PoissonRate> {
             if let Some(bid_offset) = self.dynamics.try_execute_bid(bid_price) {
                 self.inv += 1.0;
                 self.reward += bid_offset;
-                self.wealth -= bid_price;
+                self.wealth -= self.dynamics.price - bid_offset;
             }
         }
     }
 
     fn update_state(&mut self, trader_action: [f64; 2], adversary_action: f64) {
-        self.dynamics.price_dynamics.drift = adversary_action;
+        // Both channels map the action in [0, 1] symmetrically about its midpoint:
+        let signed_action = 2.0 * adversary_action - 1.0;
+
+        match self.control {
+            AdversaryControl::Drift =>
+                self.dynamics.price_dynamics.set_drift(MAX_DRIFT * signed_action),
+            AdversaryControl::FillIntensity =>
+                self.dynamics.execution_dynamics.scale =
+                    self.base_scale * MAX_INTENSITY_FACTOR.powf(signed_action),
+        }
+
         self.reward = self.inv * self.dynamics.innovate();
 
+        if let Some(noise) = self.drift_noise {
+            let drift = self.dynamics.price_dynamics.drift();
+            let reading = drift + noise * self.dynamics.sample_standard_normal();
+
+            self.observed_drift = reading.clamp(-MAX_DRIFT, MAX_DRIFT);
+        }
+
+        let mtm_variance = self.reward.powi(2);
+
         let ask_price = self.dynamics.price + trader_action[0];
         let bid_price = self.dynamics.price - trader_action[1];
 
         self.do_executions(ask_price, bid_price);
 
+        self.adversary_reward = match self.objective {
+            AdversaryObjective::NegatedPnl => -self.reward,
+            AdversaryObjective::InventoryVariance => mtm_variance,
+        };
+
         if self.is_terminal() {
             // Execute market order favourably at midprice:
             self.wealth += self.dynamics.price * self.inv;
@@ -80,20 +197,82 @@ impl ZeroSumDomain<BrownianMotionWithDrift, PoissonRate> {
     }
 
     fn is_terminal(&self) -> bool { self.dynamics.time >= 1.0 }
+
+    fn observe(&self, state: Vec<f64>) -> Observation<Vec<f64>> {
+        if self.is_terminal() {
+            Observation::Terminal(state)
+        } else {
+            Observation::Full(state)
+        }
+    }
+
+    fn adversary_state(&self) -> Vec<f64> {
+        vec![self.dynamics.time, self.inv.clamp(INV_BOUNDS[0], INV_BOUNDS[1])]
+    }
+
+    /// The adversary's view of the current state, `[time, inv]`, whatever the trader observes.
+    pub fn adversary_observation(&self) -> Observation<Vec<f64>> {
+        self.observe(self.adversary_state())
+    }
+
+    /// Recasts a transition from `step` in the adversary's terms: its own observations, its own
+    /// action and its reward under the configured objective.
+    pub fn adversary_transition(
+        &self,
+        t: Transition<Vec<f64>, ([f64; 2], f64)>,
+    ) -> Transition<Vec<f64>, f64> {
+        // The game is zero-sum only if the trader was handed exactly the reward negated here:
+        debug_assert!(
+            self.objective != AdversaryObjective::NegatedPnl
+                || t.reward + self.adversary_reward == 0.0,
+            "trader reward {} and adversary reward {} do not sum to zero",
+            t.reward, self.adversary_reward,
+        );
+
+        Transition {
+            from: t.from.map(|s| s[..2].to_vec()),
+            action: t.action.1,
+            reward: self.adversary_reward,
+            to: t.to.map(|s| s[..2].to_vec()),
+        }
+    }
+
+    pub fn time_weighted_avg_spread(&self) -> f64 {
+        if self.dynamics.time > 0.0 {
+            self.spread_integral / self.dynamics.time
+        } else {
+            0.0
+        }
+    }
+
+    /// Loose per-step reward interval for the trader.
+    pub fn reward_bounds(&self) -> (f64, f64) {
+        let inv_max = INV_BOUNDS[1].max(-INV_BOUNDS[0]) + 1.0;
+        let holding = inv_max * (self.dynamics.increment_bound() + MAX_DRIFT * self.dynamics.dt);
+        let spread = 2.0 * self.dynamics.execution_dynamics.max_offset();
+
+        (-holding, holding + spread)
+    }
+
+    pub fn clamped_actions(&self) -> usize { self.clamped_actions }
+
+    pub fn reset_clamped_actions(&mut self) { self.clamped_actions = 0; }
 }
 
-impl Domain for ZeroSumDomain<BrownianMotionWithDrift, PoissonRate> {
+impl<P, R> Domain for ZeroSumDomain<P, PoissonRate, R>
+where
+    P: PriceDynamics + SettableDrift,
+    R: Rng,
+{
     type StateSpace = ProductSpace<Interval>;
     type ActionSpace = PairSpace<TwoSpace<Reals>, Interval>;
 
     fn emit(&self) -> Observation<Vec<f64>> {
-        let state = vec![self.dynamics.time, self.inv.min(INV_BOUNDS[1]).max(INV_BOUNDS[0])];
+        let mut state = self.adversary_state();
 
-        if self.is_terminal() {
-            Observation::Terminal(state)
-        } else {
-            Observation::Full(state)
-        }
+        if self.drift_noise.is_some() { state.push(self.observed_drift); }
+
+        self.observe(state)
     }
 
     fn step(&mut self, action: ([f64; 2], f64)) -> Transition<Vec<f64>, ([f64; 2], f64)> {
@@ -103,9 +282,12 @@ impl Domain for ZeroSumDomain<BrownianMotionWithDrift, PoissonRate> {
             action.0[0].max(0.0),
             action.0[1].max(0.0)
         ];
-        let adversary_action = 10.0 * (2.0 * action.1 - 1.0);
 
-        self.update_state(trader_action, adversary_action);
+        if trader_action != action.0 { self.clamped_actions += 1; }
+
+        self.spread_integral += (trader_action[0] + trader_action[1]) * self.dynamics.dt;
+
+        self.update_state(trader_action, action.1);
 
         Transition {
             from,
@@ -116,12 +298,70 @@ impl Domain for ZeroSumDomain<BrownianMotionWithDrift, PoissonRate> {
     }
 
     fn state_space(&self) -> Self::StateSpace {
-        ProductSpace::empty()
+        let space = ProductSpace::empty()
             + Interval::bounded(0.0, 1.0)
-            + Interval::bounded(INV_BOUNDS[0], INV_BOUNDS[1])
+            + Interval::bounded(INV_BOUNDS[0], INV_BOUNDS[1]);
+
+        if self.drift_noise.is_some() {
+            space + Interval::bounded(-MAX_DRIFT, MAX_DRIFT)
+        } else {
+            space
+        }
     }
 
     fn action_space(&self) -> PairSpace<TwoSpace<Reals>, Interval> {
         PairSpace::new(TwoSpace::new([Reals; 2]), Interval::bounded(0.0, 1.0))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    const DT: f64 = 0.005;
+
+    #[test]
+    fn negated_pnl_rewards_sum_to_zero() {
+        // An intensity this large fills every quote with probability one:
+        let dynamics = ASDynamics::new(
+            DT, 100.0, StdRng::seed_from_u64(0),
+            BrownianMotionWithDrift::new(DT, 0.0, 2.0), PoissonRate::new(DT, 1e9, 1.5),
+        );
+        let mut domain = ZeroSumDomain::new(dynamics);
+
+        // Alternate single-sided quotes, so inventory builds up and the holding term is non-zero:
+        let actions = [[0.5, f64::INFINITY], [f64::INFINITY, 0.5], [f64::INFINITY, 0.2]];
+
+        for i in 0.. {
+            let action = actions[i % 3];
+            let (price_before, inv_before) = (domain.dynamics.price, domain.inv);
+
+            let t = domain.step((action, (i % 5) as f64 / 4.0));
+            let inv_after = if t.terminated() { domain.inv_terminal } else { domain.inv };
+
+            // The trader's reward rebuilt from the price move and the fills, which quote at the
+            // post-move mid and so earn exactly their offsets:
+            let holding = inv_before * (domain.dynamics.price - price_before);
+            let edge = match inv_after - inv_before {
+                d if d < 0.0 => action[0],
+                d if d > 0.0 => action[1],
+                _ => 0.0,
+            };
+            let expected = holding + edge;
+
+            assert!((t.reward - expected).abs() < 1e-9, "step {}: {} vs {}", i, t.reward, expected);
+
+            // ...and what train_zero_sum hands the adversary is its exact negation:
+            let adversary = domain.adversary_transition(t.clone());
+
+            assert!((adversary.reward + expected).abs() < 1e-9, "step {}", i);
+            assert_eq!(adversary.reward, -t.reward, "step {}", i);
+
+            if t.terminated() { break }
+        }
+
+        // Every finite quote filled, walking inventory up to its bound:
+        assert!(domain.inv_terminal >= INV_BOUNDS[1] - 1.0);
+    }
+}